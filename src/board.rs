@@ -675,6 +675,14 @@ impl Board {
         self.update_board_state();
     }
 
+    // Passes the turn to the opponent. This must not be called if the side to move is
+    // in check, so neither king is in check afterwards and the checkers remain empty.
+    pub fn apply_null_move(&mut self) {
+        // The opponent's pawn may only be captured en passant right after it advanced
+        self.remove_en_passant_square();
+        self.set_player_color(self.get_opposing_player_color());
+    }
+
     pub fn build_move_with_src_dest(
         &self,
         src: Coordinate,
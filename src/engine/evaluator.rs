@@ -208,8 +208,6 @@ static PASSED_PAWN_BONUS: [Score; 7] = [
     Score(130, 0),
 ];
 
-const KILLER_MOVE_OFFSET: i32 = 10;
-
 lazy_static! {
 static ref PIECE_POSITIONAL_VALUES: [[[[Score; 8]; 8]; 6]; 2] = {
     let mut vals = [[[[Score(0, 0); 8]; 8]; 6]; 2];
@@ -288,9 +286,6 @@ static ref KING_ATTACKERS_PENALTY: [Score; 6] = {
     table[PieceType::Queen as usize] =  Score(-80, 0);
     table
 };
-
-static ref FST_KILLER_MOVE_SCORE: i32 = get_raw_piece_value(PieceType::Pawn).get_for_phase(Phase::Midgame) - KILLER_MOVE_OFFSET;
-static ref SND_KILLER_MOVE_SCORE: i32 = get_raw_piece_value(PieceType::Pawn).get_for_phase(Phase::Midgame) - KILLER_MOVE_OFFSET * 2;
 }
 
 pub fn get_raw_piece_value(pt: PieceType) -> Score {
@@ -755,14 +750,6 @@ fn get_king_attackers_penalty(pt: PieceType) -> Score {
     KING_ATTACKERS_PENALTY[pt as usize]
 }
 
-pub fn get_nth_killer_move_score(n: usize) -> i32 {
-    match n {
-        0 => *FST_KILLER_MOVE_SCORE,
-        1 => *SND_KILLER_MOVE_SCORE,
-        _ => panic!("Invalid killer move"),
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,38 @@
+use crate::board::{Coordinate, PieceType};
+
+/// Constraints that decide when a search should end
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchLimits {
+    /// Maximum depth of iterative deepening, the search
+    /// goes as deep as it can if this is not set. Depths beyond
+    /// the deepest supported iteration are clamped to it.
+    pub depth: Option<u32>,
+    /// Maximum number of nodes to search
+    pub nodes: Option<u64>,
+    /// Exact amount of time to search for (in milliseconds)
+    pub movetime: Option<u32>,
+    /// Time allocated from the player's clock (in milliseconds),
+    /// the search may end early if it is unlikely that another
+    /// iteration can complete within this time
+    pub time_limit: Option<u32>,
+    /// Search until stopped, every other limit except for the
+    /// depth is ignored in this mode
+    pub infinite: bool,
+    /// Search for a forced mate in at most this many moves, the search
+    /// ends as soon as one is found. Pruning that could hide a mate is
+    /// disabled, so no mate exists if none is found within the limit.
+    pub mate: Option<u32>,
+    /// Restricts the search to these root moves, given as their source
+    /// square, destination square and promotion piece type. Moves that
+    /// are not legal are ignored.
+    pub search_moves: Option<Vec<(Coordinate, Coordinate, Option<PieceType>)>>,
+}
+
+impl SearchLimits {
+    pub fn new_with_depth(depth: u32) -> Self {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+}
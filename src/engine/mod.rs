@@ -1,6 +1,12 @@
 mod evaluator;
+mod limits;
+mod move_picker;
+mod params;
 mod search;
 mod tt;
 
 pub use evaluator::{get_raw_piece_value, non_pawn_material};
-pub use search::Searcher;
+pub use limits::SearchLimits;
+pub use params::SearchParams;
+pub use search::{SearchLine, SearchReport, SearchReporter, SearchResult, SearchScore, Searcher};
+pub use tt::{build_new_tt, TranspositionTable, TranspositionTableStats, DEFAULT_TT_SIZE_MB};
@@ -0,0 +1,213 @@
+use super::evaluator::static_exchange_evaluation_capture;
+use crate::board::Board;
+use crate::generator::{find_legal_move, generate_legal_captures, generate_legal_quiet_moves};
+use crate::r#move::Move;
+
+use std::mem;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stage {
+    PriorityMoves,
+    GoodCaptures,
+    Killers,
+    QuietMoves,
+    BadCaptures,
+}
+
+/// Hands out the moves of a position in the order in which they should be
+/// searched. The moves of a stage are only generated once the stage is reached,
+/// so if the first move causes a cutoff, none of the other moves are generated.
+pub struct MovePicker {
+    stage: Stage,
+    priority_moves: Vec<Move>,
+    killers: Vec<Move>,
+    // Moves of the current stage, the next move to be picked is at the end
+    stage_moves: Vec<Move>,
+    bad_captures: Vec<Move>,
+    // Moves that were handed out before their stage was generated
+    picked_moves: Vec<Move>,
+}
+
+impl MovePicker {
+    // The priority moves (e.g. the PV move and the hash move) are picked first in
+    // the given order, they are followed by
+    // 1. Captures and promotions that do not lose material, ordered by SEE
+    // 2. Killer moves, in the given order
+    // 3. Quiet moves, ordered by the score that is given when they are reached
+    // 4. Captures that lose material, ordered by SEE
+    // Priority moves and killer moves are ignored unless they are legal.
+    pub fn new(priority_moves: &[Option<Move>], killers: &[Option<Move>]) -> Self {
+        MovePicker {
+            stage: Stage::PriorityMoves,
+            priority_moves: priority_moves.iter().flatten().copied().collect(),
+            killers: killers.iter().flatten().copied().collect(),
+            stage_moves: vec![],
+            bad_captures: vec![],
+            picked_moves: vec![],
+        }
+    }
+
+    pub fn next(&mut self, board: &Board, quiet_move_score: impl Fn(&Move) -> i32) -> Option<Move> {
+        loop {
+            if let Some(m) = self.stage_moves.pop() {
+                return Some(m);
+            }
+
+            self.stage = match self.stage {
+                Stage::PriorityMoves => {
+                    // These are verified one at a time, so a cutoff by the
+                    // first one saves generating the moves of the position
+                    if !self.priority_moves.is_empty() {
+                        let m = self.priority_moves.remove(0);
+                        if let Some(m) = self.pick_if_legal(board, m, |_| true) {
+                            return Some(m);
+                        }
+                        continue;
+                    }
+                    self.stage_captures(board);
+                    Stage::GoodCaptures
+                }
+                Stage::GoodCaptures => Stage::Killers,
+                Stage::Killers => {
+                    // Captures were picked by the previous stage
+                    if !self.killers.is_empty() {
+                        let m = self.killers.remove(0);
+                        if let Some(m) =
+                            self.pick_if_legal(board, m, |m| !m.is_capture && !m.is_promotion)
+                        {
+                            return Some(m);
+                        }
+                        continue;
+                    }
+                    let mut quiet_moves =
+                        self.remove_picked_moves(generate_legal_quiet_moves(board));
+                    quiet_moves.sort_by_cached_key(|m| quiet_move_score(m));
+                    self.stage_moves = quiet_moves;
+                    Stage::QuietMoves
+                }
+                Stage::QuietMoves => {
+                    self.stage_moves = mem::take(&mut self.bad_captures);
+                    Stage::BadCaptures
+                }
+                Stage::BadCaptures => return None,
+            };
+        }
+    }
+
+    // Hands out a move that was not generated by this picker, it is only
+    // picked once and only if it is legal in the position
+    fn pick_if_legal(
+        &mut self,
+        board: &Board,
+        m: Move,
+        may_pick: impl Fn(&Move) -> bool,
+    ) -> Option<Move> {
+        if !may_pick(&m) || self.picked_moves.contains(&m) {
+            return None;
+        }
+        // The generated move has to match exactly, e.g. a killer move
+        // from another position could now be a capture
+        if find_legal_move(board, m.src, m.dest, m.promotes_to) != Some(m) {
+            return None;
+        }
+        self.picked_moves.push(m);
+        Some(m)
+    }
+
+    fn remove_picked_moves(&self, moves: Vec<Move>) -> Vec<Move> {
+        moves
+            .into_iter()
+            .filter(|m| !self.picked_moves.contains(m))
+            .collect()
+    }
+
+    fn stage_captures(&mut self, board: &Board) {
+        let mut captures = self
+            .remove_picked_moves(generate_legal_captures(board))
+            .into_iter()
+            .map(|m| (m, static_exchange_evaluation_capture(board, &m)))
+            .collect::<Vec<(Move, i32)>>();
+        captures.sort_by_key(|(_, see)| *see);
+
+        let (bad_captures, good_captures): (Vec<_>, Vec<_>) =
+            captures.into_iter().partition(|(_, see)| *see < 0);
+        self.stage_moves = good_captures.into_iter().map(|(m, _)| m).collect();
+        self.bad_captures = bad_captures.into_iter().map(|(m, _)| m).collect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::Coordinate;
+    use crate::generator::generate_legal_moves;
+    use crate::parser::parse_fen;
+
+    #[test]
+    fn test_move_picker_stages() {
+        // The knight on a4 is hanging while the pawn on d5 is defended
+        let board = parse_fen("4k3/8/4p3/3p4/n7/2N5/8/3QK3 w - - 0 1".into()).unwrap();
+        let find_move = |src, dest| find_legal_move(&board, src, dest, None).unwrap();
+        let hash_move = find_move(Coordinate::E1, Coordinate::F2);
+        let killer = find_move(Coordinate::D1, Coordinate::D3);
+        let best_quiet_move = find_move(Coordinate::D1, Coordinate::D2);
+        let legal_moves = generate_legal_moves(&board);
+
+        let mut picker = MovePicker::new(&[None, Some(hash_move)], &[Some(killer), None]);
+        let mut picked_moves = vec![];
+        while let Some(m) = picker.next(&board, |m| (*m == best_quiet_move) as i32) {
+            picked_moves.push(m);
+        }
+
+        assert_eq!(picked_moves.len(), legal_moves.len());
+        assert!(legal_moves.iter().all(|m| picked_moves.contains(m)));
+
+        assert_eq!(picked_moves[0], hash_move);
+        // Nxa4 and Qxa4 win a knight
+        assert!(picked_moves[1..3].iter().all(|m| m.dest == Coordinate::A4));
+        assert_eq!(picked_moves[3], killer);
+        assert_eq!(picked_moves[4], best_quiet_move);
+        // Nxd5 and Qxd5 lose material
+        let num_moves = picked_moves.len();
+        assert!(picked_moves[num_moves - 2..]
+            .iter()
+            .all(|m| m.dest == Coordinate::D5 && m.is_capture));
+    }
+
+    #[test]
+    fn test_move_picker_ignores_illegal_moves() {
+        let board = Board::new_starting_pos();
+        // Not legal in the starting position
+        let illegal_move = find_legal_move(
+            &parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1".into()).unwrap(),
+            Coordinate::E1,
+            Coordinate::E2,
+            None,
+        );
+        let legal_moves = generate_legal_moves(&board);
+
+        let mut picker = MovePicker::new(&[illegal_move], &[illegal_move]);
+        let mut num_picked_moves = 0;
+        while let Some(m) = picker.next(&board, |_| 0) {
+            assert_ne!(Some(m), illegal_move);
+            num_picked_moves += 1;
+        }
+        assert_eq!(num_picked_moves, legal_moves.len());
+    }
+
+    #[test]
+    fn test_move_picker_picks_moves_once() {
+        let board = Board::new_starting_pos();
+        let m = find_legal_move(&board, Coordinate::E2, Coordinate::E4, None);
+        let legal_moves = generate_legal_moves(&board);
+
+        // The same move may be both the PV move and the hash move
+        let mut picker = MovePicker::new(&[m, m], &[m]);
+        let mut picked_moves = vec![];
+        while let Some(m) = picker.next(&board, |_| 0) {
+            assert!(!picked_moves.contains(&m));
+            picked_moves.push(m);
+        }
+        assert_eq!(picked_moves.len(), legal_moves.len());
+    }
+}
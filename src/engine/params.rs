@@ -0,0 +1,32 @@
+/// Settings that change how the search explores the tree, these
+/// can be changed between searches without rebuilding the searcher
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchParams {
+    /// Extends captures that recapture on the square of the previous capture
+    pub recapture_extension: bool,
+    /// Extends pawn pushes to the seventh rank, where pawns are always passed
+    pub passed_pawn_extension: bool,
+    /// Margin per ply of remaining depth by which the static evaluation has
+    /// to beat beta for the node to be pruned by reverse futility pruning
+    pub reverse_futility_margin: i32,
+    /// Margin per ply of remaining depth by which the static evaluation has to
+    /// fall short of alpha for the node to be resolved by a quiescence search
+    pub razoring_margin: i32,
+    /// Margin by which a capture has to beat beta in the shallow search of ProbCut
+    pub probcut_margin: i32,
+    /// Searches quiet moves that give check at the first ply of the quiescence search
+    pub quiescence_checks: bool,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        SearchParams {
+            recapture_extension: true,
+            passed_pawn_extension: true,
+            reverse_futility_margin: 150,
+            razoring_margin: 400,
+            probcut_margin: 200,
+            quiescence_checks: true,
+        }
+    }
+}
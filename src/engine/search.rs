@@ -1,43 +1,196 @@
-use super::evaluator::{
-    evaluate_board, get_nth_killer_move_score, static_exchange_evaluation_capture,
-};
+use super::evaluator::{evaluate_board, get_raw_piece_value, static_exchange_evaluation_capture};
+use super::limits::SearchLimits;
+use super::move_picker::MovePicker;
+use super::params::SearchParams;
 use super::tt::{
     build_new_tt, NodeType, TranspositionTable, TranspositionTableEntry,
-    TranspositionTableEntryMoveData, TranspositionTableEntrySearchData,
+    TranspositionTableEntryMoveData, TranspositionTableEntrySearchData, TranspositionTableStats,
+    DEFAULT_TT_SIZE_MB,
+};
+use crate::board::{relative_rank, Color, Phase, Piece, PieceType};
+use crate::game::Game;
+use crate::generator::{
+    find_legal_move, generate_legal_captures, generate_legal_moves, generate_legal_quiet_moves,
+    gives_check, has_legal_move,
 };
-use crate::board::Color;
-use crate::game::{Game, GameState};
-use crate::generator::filter_non_quiescent_moves;
 use crate::r#move::Move;
 
-use std::sync::mpsc::channel;
+use lazy_static::lazy_static;
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
-use threadpool::ThreadPool;
 
 static CHECKMATE_SCORE: i32 = 320000;
 static DRAW_SCORE: i32 = 0;
 static INITIAL_ALPHA: i32 = -CHECKMATE_SCORE - 1;
 static INITIAL_BETA: i32 = CHECKMATE_SCORE + 1;
+// Minimum reduction of the search after a null move, see null_move_reduction
+static NULL_MOVE_PRUNING_R: u8 = 2;
+// The reduction grows by a ply for each time the static evaluation beats beta by this
+static NULL_MOVE_EVAL_MARGIN: i32 = 200;
+static NULL_MOVE_VERIFICATION_MIN_DEPTH: u8 = 8;
 static FUTILITY_MARGIN_1: i32 = 800; // Approximately equal to the value of a minor piece
 static FUTILITY_MARGIN_2: i32 = 1300; // Approximately equal to the value of a rook
 static DELTA_PRUNING_THRESHOLD: i32 = 2538; // Value of a queen
-static NULL_MOVE_PRUNING_R: u8 = 2;
+static ASPIRATION_WINDOW: i32 = 60; // Approximately half the value of a pawn
+static ASPIRATION_MIN_DEPTH: u8 = 3;
+static LMR_MIN_DEPTH: u8 = 3;
+// Number of moves that are searched to full depth before the rest are reduced
+static LMR_MIN_MOVES: usize = 3;
+// Number of moves that are searched before quiet moves are pruned, indexed by the remaining depth
+static LATE_MOVE_PRUNING_COUNTS: [usize; 4] = [0, 5, 8, 13];
+static SINGULAR_EXTENSION_MIN_DEPTH: u8 = 6;
+static REVERSE_FUTILITY_MAX_DEPTH: u8 = 6;
+static RAZORING_MAX_DEPTH: u8 = 3;
+static PROBCUT_MIN_DEPTH: u8 = 5;
+static PROBCUT_DEPTH_REDUCTION: u8 = 4;
+// Margin below the TT score that the other moves have to stay under, per ply of remaining depth
+static SINGULAR_EXTENSION_MARGIN: i32 = 2;
 
 const NUM_KILLER_MOVES: usize = 2;
-const MAX_SEARCH_PLIES: usize = 25;
+// Deepest ply of any line, extensions and quiescence search included.
+// Every per-ply table is sized by this.
+const MAX_SEARCH_PLIES: usize = 128;
+// Deepest iteration of iterative deepening, deeper depths are clamped to this
+pub(super) const MAX_SEARCH_DEPTH: u8 = (MAX_SEARCH_PLIES - 1) as u8;
+// Scores beyond this are mate scores, this covers mates at every ply up to MAX_SEARCH_PLIES
+const MATE_THRESHOLD: i32 = CHECKMATE_SCORE - MAX_SEARCH_PLIES as i32 - 1;
+const LMR_TABLE_SIZE: usize = 64;
+// History scores stay within [-HISTORY_MAX, HISTORY_MAX]
+const HISTORY_MAX: i32 = 16384;
+const NUM_SQUARES: usize = 64;
+const NUM_PIECES: usize = 12;
+
+lazy_static! {
+    // Number of plies by which late moves are reduced, indexed by the remaining
+    // depth and by the position of the move in the move ordering
+    static ref LATE_MOVE_REDUCTIONS: [[u8; LMR_TABLE_SIZE]; LMR_TABLE_SIZE] = {
+        let mut table = [[0; LMR_TABLE_SIZE]; LMR_TABLE_SIZE];
+        for (depth, row) in table.iter_mut().enumerate().skip(1) {
+            for (move_idx, reduction) in row.iter_mut().enumerate().skip(1) {
+                *reduction = (0.75 + (depth as f64).ln() * (move_idx as f64).ln() / 2.25) as u8;
+            }
+        }
+        table
+    };
+}
+
+/// Score of a position from the point of view of the side to move
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchScore {
+    Centipawns(i32),
+    /// Number of moves until mate, this is negative if the
+    /// side to move is the one getting mated
+    Mate(i32),
+}
+
+/// Progress updates that are published while a search is running
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchReport {
+    /// Sent every time an iteration of iterative deepening completes,
+    /// once for every line that is reported in MultiPV mode
+    Iteration {
+        depth: u8,
+        seldepth: u8,
+        /// Rank of the line among the best lines, starting from 1
+        multipv: usize,
+        score: SearchScore,
+        nodes: u64,
+        /// Permille of the transposition table that is in use
+        hashfull: u32,
+        time: Duration,
+        pv: Vec<Move>,
+    },
+    /// Sent when the search of a root move starts
+    CurrentMove {
+        depth: u8,
+        current_move: Move,
+        move_number: usize,
+    },
+}
+
+pub type SearchReporter = Arc<dyn Fn(SearchReport) + Send + Sync>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: SearchScore,
+    // Principal variation, starting with the best move
+    pub pv: Vec<Move>,
+    // Best lines of the search ordered by their score, this
+    // contains more than one line in MultiPV mode
+    pub lines: Vec<SearchLine>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchLine {
+    pub score: SearchScore,
+    pub pv: Vec<Move>,
+}
+
+// Result of searching a single root move
+struct RootMoveResult {
+    score: i32,
+    pv: Vec<Move>,
+}
 
 #[derive(Clone)]
 pub struct Searcher {
     pub game: Game,
     search_depth: u8,
     num_threads: usize,
-    nodes_searched: u32,
+    // Shared by all threads that take part in the current search
+    nodes_searched: Arc<AtomicU64>,
+    // Deepest ply that was reached, including quiescence search
+    seldepth: u8,
     tt: TranspositionTable,
     info: SearchInfo,
+    // Shared between all clones of the searcher, setting this
+    // aborts every search that is still in progress
+    stop_signal: Arc<AtomicBool>,
+    // Set when a limit of the current search is reached, this is
+    // shared by all threads that take part in the current search
+    abort_signal: Arc<AtomicBool>,
+    // Set by the owner of the searcher while the engine is pondering,
+    // the limits of the search only apply once this is cleared
+    ponder_signal: Arc<AtomicBool>,
+    // Limits that are enforced within the search tree
+    hard_time_limit: Option<Duration>,
+    node_limit: Option<u64>,
+    // Set by the main thread once it has a best move to fall back on,
+    // the limits are only enforced by every thread from then on
+    limits_enabled: Arc<AtomicBool>,
+    // Time from which the time limits are measured, this is only
+    // set once the engine is no longer pondering
+    clock_start: Arc<OnceLock<Instant>>,
+    reporter: Option<SearchReporter>,
+    // Number of best lines to report
+    multi_pv: usize,
+    // Mate searches have to prove their result, so pruning
+    // that could hide a mate is disabled during them
+    mate_search: bool,
+    params: SearchParams,
 }
 
 impl Searcher {
     pub fn new(game: Game, search_depth: u8, num_threads: usize) -> Self {
+        Searcher::new_with_tt(
+            game,
+            search_depth,
+            num_threads,
+            build_new_tt(DEFAULT_TT_SIZE_MB),
+        )
+    }
+
+    /// Creates a searcher that shares the given transposition table, this
+    /// lets the results of earlier searches be reused by later ones.
+    pub fn new_with_tt(
+        game: Game,
+        search_depth: u8,
+        num_threads: usize,
+        tt: TranspositionTable,
+    ) -> Self {
         if num_threads <= 0 {
             panic!("The engine requires at least one thread to run searches.")
         }
@@ -45,9 +198,21 @@ impl Searcher {
             game,
             search_depth,
             num_threads,
-            nodes_searched: 0,
-            tt: build_new_tt(),
+            nodes_searched: Arc::new(AtomicU64::new(0)),
+            seldepth: 0,
+            tt,
             info: SearchInfo::new(),
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            abort_signal: Arc::new(AtomicBool::new(false)),
+            ponder_signal: Arc::new(AtomicBool::new(false)),
+            hard_time_limit: None,
+            node_limit: None,
+            limits_enabled: Arc::new(AtomicBool::new(false)),
+            clock_start: Arc::new(OnceLock::new()),
+            reporter: None,
+            multi_pv: 1,
+            mate_search: false,
+            params: SearchParams::default(),
         }
     }
 
@@ -55,97 +220,282 @@ impl Searcher {
         self.info = SearchInfo::new()
     }
 
+    /// Replaces the signal used to stop searches, this allows a search
+    /// running on another thread to be stopped by its owner.
+    pub fn set_stop_signal(&mut self, stop_signal: Arc<AtomicBool>) {
+        self.stop_signal = stop_signal;
+    }
+
+    pub fn get_stop_signal(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop_signal)
+    }
+
+    /// Replaces the signal that tells the searcher that the engine is pondering.
+    /// Searches do not end on their own while this is set, and their limits
+    /// only start to apply once it is cleared.
+    pub fn set_ponder_signal(&mut self, ponder_signal: Arc<AtomicBool>) {
+        self.ponder_signal = ponder_signal;
+    }
+
+    fn is_pondering(&self) -> bool {
+        self.ponder_signal.load(Ordering::Relaxed)
+    }
+
+    // Time that has passed since the clock of the current search started,
+    // the clock only starts to run once the engine stops pondering.
+    fn elapsed_time(&self) -> Option<Duration> {
+        if self.is_pondering() {
+            return None;
+        }
+        Some(self.clock_start.get_or_init(Instant::now).elapsed())
+    }
+
+    fn is_stopped(&self) -> bool {
+        if self.stop_signal.load(Ordering::Relaxed) || self.abort_signal.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        if self.is_pondering() || !self.limits_enabled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        let node_limit_reached = self
+            .node_limit
+            .is_some_and(|limit| self.get_nodes_searched() >= limit);
+        let time_limit_reached = self
+            .hard_time_limit
+            .is_some_and(|limit| self.elapsed_time().is_some_and(|t| t >= limit));
+        if node_limit_reached || time_limit_reached {
+            // Let the other threads know that they should stop as well
+            self.abort_signal.store(true, Ordering::Relaxed);
+            return true;
+        }
+
+        false
+    }
+
+    // Blocks until the search is stopped by the owner of the stop signal,
+    // finite searches are also allowed to end once pondering is over.
+    fn wait_for_stop_signal(&self, infinite: bool) {
+        while !self.stop_signal.load(Ordering::Relaxed) && (infinite || self.is_pondering()) {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Reallocates the transposition table to take up approximately `size_mb`
+    /// megabytes, the entries of the current table are discarded.
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        if self.tt.size_mb() != size_mb {
+            self.tt = build_new_tt(size_mb);
+        }
+    }
+
+    /// Returns the counters of the transposition table, these include the
+    /// searches of earlier positions that shared it. The counters are only
+    /// updated once they are enabled with `set_tt_stats_enabled`.
+    pub fn get_tt_stats(&self) -> TranspositionTableStats {
+        self.tt.get_stats()
+    }
+
+    /// Turns the counters of the transposition table on or off, this applies
+    /// to every searcher that shares the table
+    pub fn set_tt_stats_enabled(&self, enabled: bool) {
+        self.tt.set_stats_enabled(enabled);
+    }
+
+    /// Removes every entry from the transposition table
+    pub fn clear_hash(&mut self) {
+        self.tt.clear();
+    }
+
+    /// Sets the number of best lines that searches report (MultiPV). The other
+    /// root moves only need to be refuted, so only the reported scores are exact.
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.max(1);
+    }
+
+    /// Sets the parameters that decide how the tree is explored
+    pub fn set_search_params(&mut self, params: SearchParams) {
+        self.params = params;
+    }
+
+    /// Sets the callback that receives progress updates during searches
+    pub fn set_reporter(&mut self, reporter: SearchReporter) {
+        self.reporter = Some(reporter);
+    }
+
+    fn report(&self, report: SearchReport) {
+        if let Some(reporter) = &self.reporter {
+            reporter(report);
+        }
+    }
+
     /// # Arguments
     ///
     /// * `time_limit` - Maximum search time in milliseconds
     pub fn get_best_move(&mut self, time_limit: Option<u32>) -> Result<Move, &str> {
+        let limits = SearchLimits {
+            time_limit,
+            ..SearchLimits::new_with_depth(self.search_depth.into())
+        };
+        self.search(&limits).map(|result| result.best_move)
+    }
+
+    /// Searches the current position and returns the best move
+    /// together with its score and the principal variation.
+    pub fn search(&mut self, limits: &SearchLimits) -> Result<SearchResult, &'static str> {
         self.reset_search_info();
+        self.nodes_searched = Arc::new(AtomicU64::new(0));
+        self.abort_signal = Arc::new(AtomicBool::new(false));
+        self.seldepth = 0;
+        self.limits_enabled = Arc::new(AtomicBool::new(false));
+        self.tt.new_search();
+        self.mate_search = limits.mate.is_some();
+        self.clock_start = Arc::new(OnceLock::new());
+        if !self.is_pondering() {
+            self.clock_start.get_or_init(Instant::now);
+        }
+
+        let result = self.iterative_deepening(limits);
+        // Moves made by the search discard the legal moves of the current position
+        self.game.restore_legal_moves();
+
+        // The result of an infinite search or of a search that is
+        // still pondering may only be returned once it is stopped
+        if result.is_ok() {
+            self.wait_for_stop_signal(limits.infinite);
+        }
+
+        result
+    }
+
+    fn iterative_deepening(&mut self, limits: &SearchLimits) -> Result<SearchResult, &'static str> {
+        self.game.restore_legal_moves();
         let mut legal_moves = self.game.current_legal_moves().clone();
+        if let Some(search_moves) = &limits.search_moves {
+            let allowed_moves = legal_moves
+                .iter()
+                .filter(|m| search_moves.contains(&m.to_src_dest()))
+                .copied()
+                .collect::<Vec<Move>>();
+            // If none of the given moves are legal, we search every move instead
+            if !allowed_moves.is_empty() {
+                legal_moves = allowed_moves;
+            }
+        }
         let num_legal_moves = legal_moves.len();
-        let is_white_turn = self.game.current_board().is_white_turn();
-
-        // Save zobrist to fill up the TT
-        let zobrist = self.game.get_current_zobrist();
 
         if num_legal_moves == 0 {
             return Err("No legal moves available.");
         }
 
-        if num_legal_moves == 1 {
-            return Ok(*legal_moves.get(0).unwrap());
+        // A mate search has to prove the mate even if there is only one move
+        if num_legal_moves == 1 && !limits.infinite && limits.mate.is_none() {
+            return Ok(self.unsearched_result(legal_moves[0]));
         }
 
-        // Workers will send results via tx, main thread
-        // receives results via tx
-        let (tx, rx) = channel();
+        // At least one iteration is needed to find a move
+        let mut max_search_depth = limits.depth.map_or(MAX_SEARCH_DEPTH, |depth| {
+            depth.min(MAX_SEARCH_DEPTH as u32) as u8
+        });
+        if let Some(mate) = limits.mate {
+            // A mate in N moves is found within 2N - 1 plies
+            let mate_depth = (2 * mate).saturating_sub(1).min(u8::MAX as u32) as u8;
+            max_search_depth = max_search_depth.min(mate_depth);
+        }
+        let max_search_depth = max_search_depth.max(1);
 
-        let max_search_depth = self.search_depth;
-        let mut best_move: Option<Move> = None;
+        // Time limits do not apply to infinite searches
+        let (time_limit, movetime) = if limits.infinite {
+            (None, None)
+        } else {
+            (
+                limits.time_limit.map(|l| Duration::from_millis(l as u64)),
+                limits.movetime.map(|l| Duration::from_millis(l as u64)),
+            )
+        };
+        self.hard_time_limit = time_limit.into_iter().chain(movetime).min();
+        self.node_limit = if limits.infinite { None } else { limits.nodes };
+
+        // Lazy SMP: helper threads search the same tree as the main thread and
+        // only share what they find with it through the transposition table.
+        // Helpers beyond the number of cores only take time away from the main thread
+        let num_cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let num_helpers = self.num_threads.min(num_cores) - 1;
+        std::thread::scope(|scope| {
+            for helper_idx in 0..num_helpers {
+                let mut helper = self.clone();
+                helper.reporter = None;
+                helper.multi_pv = 1;
+                let root_moves = legal_moves.clone();
+                std::thread::Builder::new()
+                    .name(format!("requin_helper_{}", helper_idx + 1))
+                    .spawn_scoped(scope, move || {
+                        helper.helper_iterative_deepening(root_moves, max_search_depth, helper_idx)
+                    })
+                    .expect("Unable to spawn a search thread.");
+            }
+
+            let result =
+                self.main_iterative_deepening(limits, legal_moves, max_search_depth, time_limit);
+            // Helpers keep searching until the main thread is done
+            self.abort_signal.store(true, Ordering::Relaxed);
+            Ok(result)
+        })
+    }
+
+    // Iterative deepening of the main thread, which decides
+    // when the search ends and reports its results
+    fn main_iterative_deepening(
+        &mut self,
+        limits: &SearchLimits,
+        mut root_moves: Vec<Move>,
+        max_search_depth: u8,
+        time_limit: Option<Duration>,
+    ) -> SearchResult {
+        // Save zobrist to fill up the TT
+        let zobrist = self.game.get_current_zobrist();
+        let mut best_result: Option<SearchResult> = None;
+        let mut prev_score: Option<i32> = None;
         let start_time = Instant::now();
-        let time_limit = time_limit.map(|l| Duration::from_millis(l as u64));
-        let deadline = time_limit.map(|l| start_time + l);
 
         // Iterative deepening
-        for current_search_depth in 0..=(max_search_depth - 1) {
-            let elapsed_time = Instant::now().duration_since(start_time);
-
+        for current_search_depth in 0..max_search_depth {
             // Consider skipping the current iteration if the time situation is not good
-            if time_limit.is_some() && best_move.is_some() {
+            if let (Some(time_limit), Some(elapsed_time), Some(_)) =
+                (time_limit, self.elapsed_time(), &best_result)
+            {
                 // Check if 50% of allocated time has been used
-                if elapsed_time.div_duration_f32(time_limit.unwrap()) > 0.5 {
-                    return Ok(best_move.unwrap());
+                if elapsed_time.div_duration_f32(time_limit) > 0.5 {
+                    return best_result.unwrap();
                 }
             }
 
-            let pool = ThreadPool::with_name("requin_searchers".to_string(), self.num_threads);
-            // Search the best move first, this is useful when the num of available threads is low.
-            legal_moves.sort_by_key(|m| if Some(m) == best_move.as_ref() { 0 } else { 1 });
-            for m in &legal_moves {
-                let tx = tx.clone();
-                let mut searcher = self.clone();
-                let m = m.clone();
-                searcher.game.apply_move(&m);
-                pool.execute(move || {
-                    // Whether a move can be pruned depends on whether it is a capture
-                    let curr_eval = -searcher.alpha_beta(
-                        current_search_depth,
-                        INITIAL_ALPHA,
-                        INITIAL_BETA,
-                        is_white_turn,
-                        !m.is_capture,
-                        0, // Start with search depth 0 (zero-indexed)
-                    );
-
-                    tx.send((m, curr_eval))
-                        .expect("Unexpected error: Main thread is not receiving.");
-                });
+            // Limits are only enforced once we have a best move to fall back on
+            if best_result.is_some() {
+                self.limits_enabled.store(true, Ordering::Relaxed);
             }
-
-            // We only apply the deadline if we already have a best move, since we need
-            // to forcefully return the best move if we breach the deadline
-            let mut move_evals: Vec<(Move, i32)> = if deadline.is_some() && best_move.is_some() {
-                let mut res = Vec::with_capacity(num_legal_moves);
-                for _ in 0..num_legal_moves {
-                    match rx.recv_deadline(deadline.unwrap()) {
-                        Ok(m) => {
-                            res.push(m);
-                        }
-                        Err(_) => {
-                            // If we timeout, then return the best move that currently have
-                            return Ok(best_move.unwrap());
+            // The selective depth is reported for each iteration on its own
+            self.seldepth = 0;
+
+            // The evaluations of an interrupted iteration cannot be trusted,
+            // so we fall back to the result of the last completed iteration.
+            let results =
+                match self.aspiration_search(&root_moves, current_search_depth, prev_score) {
+                    Some(results) => results,
+                    None => {
+                        return match best_result {
+                            Some(result) => result,
+                            None => self.unsearched_result(root_moves[0]),
                         }
                     }
-                }
-                res
-            } else {
-                // Assuming that all moves are evaluated successfully without fail
-                rx.iter().take(num_legal_moves).collect()
-            };
-
-            move_evals.sort_by(|(_, e1), (_, e2)| e2.cmp(e1));
+                };
+            // The next iteration starts with the moves that did best in this one
+            root_moves = results.iter().map(|r| r.pv[0]).collect();
 
-            let candidate_move = move_evals[0].0;
-            let candidate_move_score = move_evals[0].1;
+            let candidate_move = results[0].pv[0];
+            let candidate_move_score = results[0].score;
+            prev_score = Some(candidate_move_score);
 
             // Insert into TT
             self.tt.set_entry(
@@ -155,77 +505,351 @@ impl Searcher {
                     zobrist,
                     current_search_depth,
                     candidate_move_score,
+                    // The root is one ply above the positions after the root moves
+                    -1,
                     NodeType::PV,
                 ),
             );
-            best_move = Some(candidate_move);
+
+            let lines = results
+                .into_iter()
+                .take(self.multi_pv)
+                .map(|r| SearchLine {
+                    score: to_search_score(r.score),
+                    pv: r.pv,
+                })
+                .collect::<Vec<SearchLine>>();
+            self.info.prev_pv = lines[0].pv.clone();
+
+            let time = Instant::now().duration_since(start_time);
+            let hashfull = self.tt.hashfull();
+            for (line_idx, line) in lines.iter().enumerate() {
+                self.report(SearchReport::Iteration {
+                    depth: current_search_depth + 1,
+                    seldepth: self.seldepth,
+                    multipv: line_idx + 1,
+                    score: line.score,
+                    nodes: self.get_nodes_searched(),
+                    hashfull,
+                    time,
+                    pv: line.pv.clone(),
+                });
+            }
+
+            best_result = Some(SearchResult {
+                best_move: candidate_move,
+                score: lines[0].score,
+                pv: lines[0].pv.clone(),
+                lines,
+            });
+
+            // Iterative deepening finds the shortest mate first, so there is no need
+            // to search any further. Mates that were found by the quiescence search
+            // are only accepted once the full width search confirms them, since
+            // their lines are not recorded in the PV.
+            if let (Some(mate), Some(SearchScore::Mate(moves))) =
+                (limits.mate, best_result.as_ref().map(|r| r.score))
+            {
+                let is_proven = moves > 0
+                    && moves as u32 <= mate
+                    && 2 * moves as u32 - 1 <= current_search_depth as u32 + 1;
+                if is_proven {
+                    break;
+                }
+            }
+
+            // Avoid starting an iteration that would be aborted right away
+            if !self.is_pondering()
+                && self
+                    .node_limit
+                    .is_some_and(|limit| self.get_nodes_searched() >= limit)
+            {
+                break;
+            }
+        }
+
+        best_result.unwrap()
+    }
+
+    // Iterative deepening of a helper thread. Every other helper skips the
+    // even depths, so that the threads are spread over different depths.
+    fn helper_iterative_deepening(
+        &mut self,
+        mut root_moves: Vec<Move>,
+        max_search_depth: u8,
+        helper_idx: usize,
+    ) {
+        let mut prev_score: Option<i32> = None;
+        for current_search_depth in 0..max_search_depth {
+            if helper_idx % 2 == 1 && current_search_depth % 2 == 0 {
+                continue;
+            }
+            match self.aspiration_search(&root_moves, current_search_depth, prev_score) {
+                Some(results) => {
+                    root_moves = results.iter().map(|r| r.pv[0]).collect();
+                    prev_score = Some(results[0].score);
+                    self.info.prev_pv = results[0].pv.clone();
+                }
+                None => return,
+            }
+        }
+    }
+
+    // Searches the root moves within a window around the score of the previous
+    // iteration, which is widened for as long as the best score falls outside of it
+    fn aspiration_search(
+        &mut self,
+        root_moves: &[Move],
+        current_search_depth: u8,
+        prev_score: Option<i32>,
+    ) -> Option<Vec<RootMoveResult>> {
+        let mut delta = ASPIRATION_WINDOW;
+        // Windows are only used once the scores have settled, and the worst
+        // of several lines in MultiPV mode cannot be kept within one
+        let (mut alpha, mut beta) = match prev_score {
+            Some(score)
+                if current_search_depth >= ASPIRATION_MIN_DEPTH
+                    && self.multi_pv == 1
+                    && score.abs() < MATE_THRESHOLD =>
+            {
+                (score - delta, score + delta)
+            }
+            _ => (INITIAL_ALPHA, INITIAL_BETA),
+        };
+
+        loop {
+            let results = self.search_root_moves(root_moves, current_search_depth, alpha, beta)?;
+            let best_score = results[0].score;
+            if best_score <= alpha && alpha > INITIAL_ALPHA {
+                alpha = (alpha - delta).max(INITIAL_ALPHA);
+            } else if best_score >= beta && beta < INITIAL_BETA {
+                beta = (beta + delta).min(INITIAL_BETA);
+            } else {
+                return Some(results);
+            }
+            delta *= 2;
+        }
+    }
+
+    // Searches the root moves within the window and returns their results ordered
+    // by score, or None if the search was stopped. Only the best `multi_pv` moves
+    // get an exact score, the other moves fail low against the worst of them. The
+    // search ends early when a move fails high.
+    fn search_root_moves(
+        &mut self,
+        root_moves: &[Move],
+        current_search_depth: u8,
+        alpha: i32,
+        beta: i32,
+    ) -> Option<Vec<RootMoveResult>> {
+        let is_white_turn = self.game.current_board().is_white_turn();
+        self.info.root_depth = current_search_depth + 1;
+        let mut results: Vec<RootMoveResult> = Vec::with_capacity(root_moves.len());
+        let mut best_scores: Vec<i32> = Vec::with_capacity(root_moves.len());
+
+        for (move_idx, m) in root_moves.iter().enumerate() {
+            self.report(SearchReport::CurrentMove {
+                depth: current_search_depth + 1,
+                current_move: *m,
+                move_number: move_idx + 1,
+            });
+            // Only the previous best move leads along the previous PV
+            self.info.follow_pv = self.info.prev_pv.first() == Some(m);
+            self.info.prev_moves[0] = Some(*m);
+            self.game.make_move(m);
+            // Whether a move can be pruned depends on whether it is a capture
+            let score = if best_scores.len() < self.multi_pv {
+                -self.alpha_beta(
+                    current_search_depth,
+                    -beta,
+                    -alpha,
+                    is_white_turn,
+                    !m.is_capture,
+                    0, // Start with search depth 0 (zero-indexed)
+                )
+            } else {
+                // The move only needs an exact score if it beats one of the best lines
+                let alpha = alpha.max(best_scores[self.multi_pv - 1]);
+                let score = -self.alpha_beta(
+                    current_search_depth,
+                    -alpha - 1,
+                    -alpha,
+                    is_white_turn,
+                    !m.is_capture,
+                    0,
+                );
+                if score > alpha && score < beta {
+                    -self.alpha_beta(
+                        current_search_depth,
+                        -beta,
+                        -alpha,
+                        is_white_turn,
+                        !m.is_capture,
+                        0,
+                    )
+                } else {
+                    score
+                }
+            };
+            self.game.unmake_move();
+
+            if self.is_stopped() {
+                return None;
+            }
+
+            let mut pv = vec![*m];
+            pv.extend(self.info.get_pv(0));
+            self.extend_pv_from_tt(&mut pv, current_search_depth as usize + 1);
+            results.push(RootMoveResult { score, pv });
+            let idx = best_scores.partition_point(|&s| s >= score);
+            best_scores.insert(idx, score);
+
+            if score >= beta {
+                break;
+            }
+        }
+
+        // The sort is stable, so moves that failed low are ranked below
+        // the exact scores that they are tied with
+        results.sort_by_key(|r| Reverse(r.score));
+        Some(results)
+    }
+
+    // The PV of a line that ends in a TT cutoff is incomplete, so it is continued
+    // with the moves stored in the TT, up to the given number of moves.
+    fn extend_pv_from_tt(&mut self, pv: &mut Vec<Move>, max_len: usize) {
+        if pv.len() >= max_len {
+            return;
+        }
+        for m in pv.iter() {
+            self.game.make_move(m);
+        }
+
+        while pv.len() < max_len {
+            let zobrist = self.game.get_current_zobrist();
+            let tt_entry = self.tt.get_entry(zobrist);
+            if !tt_entry.is_valid(zobrist) || tt_entry.get_search_data().node_type() != NodeType::PV
+            {
+                break;
+            }
+            match self.get_hash_move(&tt_entry) {
+                Some(m) => {
+                    self.game.make_move(&m);
+                    pv.push(m);
+                }
+                _ => break,
+            }
         }
 
-        Ok(best_move.unwrap())
+        for _ in 0..pv.len() {
+            self.game.unmake_move();
+        }
+    }
+
+    fn get_hash_move(&self, tt_entry: &TranspositionTableEntry) -> Option<Move> {
+        let tt_move_data = tt_entry.get_move_data();
+        let ppt = if tt_move_data.best_move_is_promotion() {
+            Some(tt_move_data.best_move_ppt())
+        } else {
+            None
+        };
+        // Moves from the TT are only used if they are legal in the current position
+        find_legal_move(
+            self.game.current_board(),
+            tt_move_data.best_move_src(),
+            tt_move_data.best_move_dest(),
+            ppt,
+        )
+    }
+
+    // Result for a move that was chosen without searching, the
+    // score is the static evaluation of the current position.
+    fn unsearched_result(&self, m: Move) -> SearchResult {
+        let offset = if self.game.current_board().is_white_turn() {
+            1
+        } else {
+            -1
+        };
+        let score = to_search_score(offset * evaluate_board(self.game.current_board()));
+        SearchResult {
+            best_move: m,
+            score,
+            pv: vec![m],
+            lines: vec![SearchLine { score, pv: vec![m] }],
+        }
     }
 
     // Inspired by https://www.chessprogramming.org/Alpha-Beta
     // Alpha-beta pruning in the negamax framework
     pub fn alpha_beta(
         &mut self,
-        remaining_depth: u8,
+        mut remaining_depth: u8,
         mut alpha: i32,
         beta: i32,
         is_white: bool,
         can_prune: bool,
         searched_depth: u8,
     ) -> i32 {
-        match self.game.state {
-            GameState::InProgress => {}
-            GameState::WhiteWon | GameState::BlackWon => {
-                return -(CHECKMATE_SCORE - searched_depth as i32)
-            }
-            GameState::Stalemate => return DRAW_SCORE,
+        // The score of an aborted search is discarded, so any value will do
+        if self.is_stopped() {
+            return 0;
         }
+        let can_prune = can_prune && !self.mate_search;
+        self.seldepth = self.seldepth.max(searched_depth + 1);
+        // The PV of this node is rebuilt from scratch
+        self.info.pv_length[searched_depth as usize] = 0;
+
         if self.game.is_threefold_repetition() {
             return DRAW_SCORE;
         }
 
+        // Check extension, forcing lines are searched deeper and
+        // the side to move is never left in check at the horizon
+        if self.game.is_in_check() && self.may_extend(remaining_depth, searched_depth) {
+            remaining_depth += 1;
+        }
+
         if remaining_depth == 0 || (searched_depth as usize) == MAX_SEARCH_PLIES {
-            return self.quiesce(alpha, beta, is_white, searched_depth);
-        } else if remaining_depth == 1 {
-            // Futility pruning
-            let offset = if is_white { -1 } else { 1 };
-            let eval = offset * evaluate_board(self.game.current_board());
-            // If a move proves to be futile, we just return alpha since
-            // further continuations are unlikely to raise alpha
-            if eval + FUTILITY_MARGIN_1 < alpha && can_prune {
-                return alpha;
-            }
-        } else if remaining_depth == 2 {
-            // Extended futility pruning
-            let offset = if is_white { -1 } else { 1 };
-            let eval = offset * evaluate_board(self.game.current_board());
-            // If a move proves to be futile, we just return alpha since
-            // further continuations are unlikely to raise alpha
-            if eval + FUTILITY_MARGIN_2 < alpha && can_prune {
-                return alpha;
-            }
+            return self.quiesce(
+                alpha,
+                beta,
+                is_white,
+                searched_depth,
+                self.params.quiescence_checks,
+            );
         }
 
+        // Set while checking whether the hash move is singular, the
+        // results of that search must not be mistaken for this node's
+        let excluded_move = self.info.excluded_moves[searched_depth as usize];
+
         // To be used to read and write to TT
         let zobrist = self.game.get_current_zobrist();
         let tt_entry = self.tt.get_entry(zobrist);
         let hash_move = if tt_entry.is_valid(zobrist) {
             let tt_search_data = tt_entry.get_search_data();
             // If this move has already been searched before, just return the score
+            let tt_score = score_from_tt(tt_search_data.score(), searched_depth as i32);
+            // Entries may have been stored by earlier searches that
+            // used pruning, so they cannot prove mates
             if tt_search_data.depth() >= remaining_depth
-                && tt_search_data.node_type() == NodeType::PV
+                && !self.mate_search
+                && excluded_move.is_none()
             {
                 match tt_search_data.node_type() {
-                    NodeType::PV => return tt_search_data.score(),
+                    NodeType::PV => {
+                        self.tt.record_cutoff();
+                        return tt_score;
+                    }
                     NodeType::Cut => {
-                        if tt_search_data.score() >= beta {
+                        if tt_score >= beta {
+                            self.tt.record_cutoff();
                             return beta;
                         }
                     }
                     NodeType::All => {
-                        if tt_search_data.score() <= alpha {
+                        if tt_score <= alpha {
+                            self.tt.record_cutoff();
                             return alpha;
                         }
                     }
@@ -235,20 +859,7 @@ impl Searcher {
             if tt_search_data.node_type() == NodeType::PV
                 || tt_search_data.node_type() == NodeType::Cut
             {
-                let tt_move_data = tt_entry.get_move_data();
-                let ppt = if tt_move_data.best_move_is_promotion() {
-                    Some(tt_move_data.best_move_ppt())
-                } else {
-                    None
-                };
-                self.game
-                    .current_board()
-                    .build_move_with_src_dest(
-                        tt_move_data.best_move_src(),
-                        tt_move_data.best_move_dest(),
-                        ppt,
-                    )
-                    .ok()
+                self.get_hash_move(&tt_entry)
             } else {
                 None
             }
@@ -256,205 +867,597 @@ impl Searcher {
             None
         };
 
-        // Move ordering
-        // 1. Hash move
-        // 2. Good captures
-        // 3. Killer moves
-        // 4. Bad captures
-        // 5. Non-captures
-        let mut legal_moves = self
-            .game
-            .current_legal_moves()
-            .clone()
-            .into_iter()
-            .map(|m| {
-                (
-                    m,
-                    if hash_move.is_some() && hash_move.unwrap() == m {
-                        // Test the hash move first
-                        // TODO: Test the hash move without generating other moves
-                        i32::MAX
-                    } else if m.is_capture {
-                        static_exchange_evaluation_capture(self.game.current_board(), &m)
-                    } else if let Some(n) = self.is_killer_move_at_ply(&m, searched_depth) {
-                        get_nth_killer_move_score(n as usize)
-                    } else {
-                        // Give non-captures a low score for them to be evaluated last
-                        i32::MIN
-                    },
-                )
-            })
-            .collect::<Vec<(Move, i32)>>();
-
-        legal_moves.sort_by(|(_, score1), (_, score2)| score2.cmp(score1));
+        let in_check = self.game.is_in_check();
+        // Evaluation of the position for the side to move, pruning
+        // based on it is never done when evading a check
+        let static_eval = if !in_check && !self.mate_search {
+            let offset = if is_white { -1 } else { 1 };
+            Some(offset * evaluate_board(self.game.current_board()))
+        } else {
+            None
+        };
 
-        // Maybe do null move pruning
-        if self.may_do_null_move_pruning(remaining_depth, is_white) {
-            self.game.apply_null_move();
-            // Do an alpha beta search with reduced depth
-            let score = -self.alpha_beta(
-                remaining_depth - NULL_MOVE_PRUNING_R,
-                -beta,
-                -alpha,
-                !is_white,
-                false,
-                searched_depth + 1,
-            );
-            self.game.undo_move();
-            if score >= beta {
+        // Nodes on the PV and nodes whose window contains a mate score are searched in full
+        let is_pv_node = beta - alpha > 1;
+        if let Some(eval) = static_eval.filter(|_| {
+            can_prune
+                && !is_pv_node
+                && excluded_move.is_none()
+                && alpha > -MATE_THRESHOLD
+                && beta < MATE_THRESHOLD
+        }) {
+            let depth = remaining_depth as i32;
+
+            // Reverse futility pruning, the side to move is so far ahead that
+            // the opponent is unlikely to catch up before the horizon
+            if remaining_depth <= REVERSE_FUTILITY_MAX_DEPTH
+                && eval - self.params.reverse_futility_margin * depth >= beta
+            {
                 return beta;
             }
-        }
-
-        let mut best_move: Option<Move> = None;
-
-        for (m, _) in legal_moves {
-            // TODO: Fix how null move pruning makes this value more than
-            // what it should be
-            self.nodes_searched += 1;
-
-            self.game.apply_move(&m);
-            // Whether or not a node can be pruned depends on whether
-            // the move was a 'peaceful' move
-            let score = -self.alpha_beta(
-                remaining_depth - 1,
-                -beta,
-                -alpha,
-                !is_white,
-                !m.is_capture,
-                searched_depth + 1,
-            );
-            self.game.undo_move();
 
-            if score >= beta {
-                self.tt.set_entry(
-                    zobrist,
-                    build_tt_entry(
-                        best_move.as_ref(),
-                        zobrist,
-                        remaining_depth as u8,
-                        score,
-                        NodeType::Cut,
-                    ),
+            // Razoring, the side to move is so far behind that only captures could save
+            // it, so the node is cut if the quiescence search cannot raise alpha either
+            if remaining_depth <= RAZORING_MAX_DEPTH
+                && eval + self.params.razoring_margin * depth < alpha
+            {
+                let score = self.quiesce(
+                    alpha,
+                    alpha + 1,
+                    is_white,
+                    searched_depth,
+                    self.params.quiescence_checks,
                 );
-                self.store_killer_move(m, searched_depth);
-                return beta;
+                if self.is_stopped() {
+                    return 0;
+                }
+                if score <= alpha {
+                    return alpha;
+                }
             }
 
-            if score > alpha {
-                best_move = Some(m);
-                alpha = score;
+            // ProbCut, a capture that beats beta by a margin in a shallow
+            // search will most likely beat beta in the full search as well
+            if remaining_depth >= PROBCUT_MIN_DEPTH {
+                let probcut_beta = beta + self.params.probcut_margin;
+                if self.probcut(
+                    probcut_beta,
+                    eval,
+                    remaining_depth,
+                    is_white,
+                    searched_depth,
+                ) {
+                    return beta;
+                }
+                if self.is_stopped() {
+                    return 0;
+                }
             }
         }
 
-        self.tt.set_entry(
-            zobrist,
-            build_tt_entry(
-                best_move.as_ref(),
-                zobrist,
-                remaining_depth as u8,
-                alpha,
-                if best_move.is_some() {
-                    NodeType::PV
+        // Futility pruning (extended futility pruning two plies from the horizon)
+        // If the position is futile, quiet moves are unlikely to raise alpha, so only
+        // captures, promotions and checks are searched. Checks are kept as they
+        // could lead to a mate that the evaluation does not see.
+        let is_futile = match static_eval {
+            Some(eval) if can_prune && remaining_depth <= 2 => {
+                let margin = if remaining_depth == 1 {
+                    FUTILITY_MARGIN_1
+                } else {
+                    FUTILITY_MARGIN_2
+                };
+                eval + margin < alpha
+            }
+            _ => false,
+        };
+
+        // Singular extension, the hash move is extended if a reduced search without it
+        // shows that every other move falls short of the TT score by some margin
+        let singular_move = match hash_move {
+            Some(hash_move)
+                if excluded_move.is_none()
+                    && remaining_depth >= SINGULAR_EXTENSION_MIN_DEPTH
+                    && self.may_extend(remaining_depth, searched_depth) =>
+            {
+                let tt_search_data = tt_entry.get_search_data();
+                let tt_score = score_from_tt(tt_search_data.score(), searched_depth as i32);
+                // Entries of hash moves are either exact scores or lower bounds
+                if tt_search_data.depth() + 3 >= remaining_depth && tt_score.abs() < MATE_THRESHOLD
+                {
+                    let singular_beta =
+                        tt_score - SINGULAR_EXTENSION_MARGIN * remaining_depth as i32;
+                    let is_singular = self.is_singular_move(
+                        hash_move,
+                        singular_beta,
+                        remaining_depth,
+                        is_white,
+                        can_prune,
+                        searched_depth,
+                    );
+                    if self.is_stopped() {
+                        return 0;
+                    }
+                    is_singular.then_some(hash_move)
                 } else {
-                    NodeType::All
-                },
-            ),
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        let pv_move = if self.info.follow_pv {
+            self.info.get_prev_pv_move(searched_depth)
+        } else {
+            None
+        };
+        let prev_move = self.info.prev_moves[searched_depth as usize];
+        let countermove = prev_move.and_then(|prev_move| self.info.get_countermove(&prev_move));
+
+        // The move from the PV of the previous iteration and the hash move are tried
+        // before the other moves are generated, see MovePicker for the order
+        let killers = self.info.killer_moves[searched_depth as usize];
+        let mut move_picker = MovePicker::new(
+            &[pv_move, hash_move],
+            &[killers[0], killers[1], countermove],
         );
 
+        // Maybe do null move pruning, if passing the turn still beats beta, one of the
+        // moves most likely does as well. Null moves hide mates in zugzwang positions,
+        // and a second null move in a row would only search the same position again.
+        let after_null_move = searched_depth > 0 && prev_move.is_none();
+        if !self.mate_search
+            && !is_pv_node
+            && !after_null_move
+            && !self.info.verifying_null_move
+            && excluded_move.is_none()
+            && static_eval.is_some_and(|eval| eval >= beta)
+            && self.may_do_null_move_pruning(remaining_depth, is_white)
+        {
+            let reduction = null_move_reduction(remaining_depth, static_eval.unwrap() - beta);
+            // The null move is never part of the PV
+            let follow_pv = self.info.follow_pv;
+            self.info.follow_pv = false;
+            self.info.prev_moves[searched_depth as usize + 1] = None;
+            self.game.make_null_move();
+            // Do an alpha beta search with reduced depth
+            let score = -self.alpha_beta(
+                remaining_depth - reduction,
+                -beta,
+                -beta + 1,
+                !is_white,
+                false,
+                searched_depth + 1,
+            );
+            self.game.unmake_move();
+            self.info.follow_pv = follow_pv;
+            if self.is_stopped() {
+                return 0;
+            }
+            if score >= beta {
+                if remaining_depth < NULL_MOVE_VERIFICATION_MIN_DEPTH {
+                    return beta;
+                }
+                // Zugzwang is more likely to go unnoticed in deep searches, so the cutoff is
+                // only trusted once a search of this node without null moves confirms it
+                self.info.verifying_null_move = true;
+                let score = self.alpha_beta(
+                    remaining_depth - reduction,
+                    beta - 1,
+                    beta,
+                    is_white,
+                    can_prune,
+                    searched_depth,
+                );
+                self.info.verifying_null_move = false;
+                if self.is_stopped() {
+                    return 0;
+                }
+                if score >= beta {
+                    return beta;
+                }
+            }
+        }
+
+        let mut best_move: Option<Move> = None;
+        let mut num_searched_moves = 0;
+        // Quiet moves that did not cause a beta cutoff
+        let mut searched_quiet_moves: Vec<Move> = vec![];
+        // Late moves are unlikely to be good, so they are pruned or searched to
+        // a reduced depth. This is not done when evading a check.
+        let may_prune_late_moves = !in_check && !self.mate_search;
+
+        let mut num_picked_moves = 0;
+        while let Some(m) = move_picker.next(self.game.current_board(), |m| {
+            self.info.get_history_score(m, prev_move.as_ref())
+        }) {
+            if excluded_move == Some(m) {
+                continue;
+            }
+            let move_idx = num_picked_moves;
+            num_picked_moves += 1;
+            // TODO: Fix how null move pruning makes this value more than
+            // what it should be
+            self.nodes_searched.fetch_add(1, Ordering::Relaxed);
+
+            // Only the PV move leads along the PV of the previous iteration
+            self.info.follow_pv = self.info.follow_pv && pv_move == Some(m);
+
+            self.info.prev_moves[searched_depth as usize + 1] = Some(m);
+            self.game.make_move(&m);
+            // Moves that give check are extended by the node that follows
+            let extension = if !self.game.is_in_check()
+                && self.may_extend(remaining_depth, searched_depth)
+                && (singular_move == Some(m)
+                    || is_extended_move(&m, prev_move.as_ref(), &self.params))
+            {
+                1
+            } else {
+                0
+            };
+            let new_depth = remaining_depth - 1 + extension;
+            // Extended moves are never pruned or reduced
+            let is_quiet_move =
+                !m.is_capture && !m.is_promotion && !self.game.is_in_check() && extension == 0;
+            if is_futile && is_quiet_move {
+                self.game.unmake_move();
+                continue;
+            }
+
+            // Late move pruning, the position is not lost, so there
+            // is no need to look for a defence among the quiet moves
+            if may_prune_late_moves
+                && !is_pv_node
+                && is_quiet_move
+                && (remaining_depth as usize) < LATE_MOVE_PRUNING_COUNTS.len()
+                && move_idx >= LATE_MOVE_PRUNING_COUNTS[remaining_depth as usize]
+                && alpha > -MATE_THRESHOLD
+            {
+                self.game.unmake_move();
+                continue;
+            }
+            // Whether or not a node can be pruned depends on whether
+            // the move was a 'peaceful' move
+            let score = if num_searched_moves == 0 {
+                -self.alpha_beta(
+                    new_depth,
+                    -beta,
+                    -alpha,
+                    !is_white,
+                    !m.is_capture,
+                    searched_depth + 1,
+                )
+            } else {
+                // Late move reductions
+                let reduction = if may_prune_late_moves
+                    && is_quiet_move
+                    && remaining_depth >= LMR_MIN_DEPTH
+                    && move_idx >= LMR_MIN_MOVES
+                    && self.is_killer_move_at_ply(&m, searched_depth).is_none()
+                {
+                    late_move_reduction(remaining_depth, move_idx)
+                } else {
+                    0
+                };
+                // Principal variation search: the first move is expected to be the
+                // best, so the others are searched with a null window that can only
+                // prove them worse. Moves that turn out better are searched again.
+                let mut score = -self.alpha_beta(
+                    new_depth - reduction,
+                    -alpha - 1,
+                    -alpha,
+                    !is_white,
+                    !m.is_capture,
+                    searched_depth + 1,
+                );
+                // A reduced move that beats alpha is searched to full depth
+                if reduction > 0 && score > alpha {
+                    score = -self.alpha_beta(
+                        new_depth,
+                        -alpha - 1,
+                        -alpha,
+                        !is_white,
+                        !m.is_capture,
+                        searched_depth + 1,
+                    );
+                }
+                if score > alpha && score < beta {
+                    -self.alpha_beta(
+                        new_depth,
+                        -beta,
+                        -alpha,
+                        !is_white,
+                        !m.is_capture,
+                        searched_depth + 1,
+                    )
+                } else {
+                    score
+                }
+            };
+            self.game.unmake_move();
+            num_searched_moves += 1;
+
+            // Avoid polluting the TT and killer moves with the
+            // results of an incomplete search
+            if self.is_stopped() {
+                return 0;
+            }
+
+            if score >= beta {
+                if excluded_move.is_none() {
+                    self.tt.set_entry(
+                        zobrist,
+                        build_tt_entry(
+                            Some(&m),
+                            zobrist,
+                            remaining_depth as u8,
+                            score,
+                            searched_depth as i32,
+                            NodeType::Cut,
+                        ),
+                    );
+                }
+                if !m.is_capture && !m.is_promotion {
+                    self.store_killer_move(m, searched_depth);
+                    self.info.update_quiet_move_history(
+                        &m,
+                        &searched_quiet_moves,
+                        prev_move.as_ref(),
+                        remaining_depth,
+                    );
+                }
+                return beta;
+            }
+            if !m.is_capture && !m.is_promotion {
+                searched_quiet_moves.push(m);
+            }
+
+            if score > alpha {
+                best_move = Some(m);
+                alpha = score;
+                self.info.update_pv(m, searched_depth);
+            }
+        }
+
+        // Positions are not checked for a mate or a stalemate when they are
+        // reached, so it is only noticed here that there are no legal moves
+        if num_picked_moves == 0 && excluded_move.is_none() {
+            return if in_check {
+                -(CHECKMATE_SCORE - searched_depth as i32)
+            } else {
+                DRAW_SCORE
+            };
+        }
+
+        if excluded_move.is_none() {
+            self.tt.set_entry(
+                zobrist,
+                build_tt_entry(
+                    best_move.as_ref(),
+                    zobrist,
+                    remaining_depth as u8,
+                    alpha,
+                    searched_depth as i32,
+                    if best_move.is_some() {
+                        NodeType::PV
+                    } else {
+                        NodeType::All
+                    },
+                ),
+            );
+        }
+
         alpha
     }
 
+    // Searches captures until the position is quiet, every evasion is searched
+    // when in check. Quiet moves that give check are searched as well if
+    // include_quiet_checks is set, this is only done at the first ply.
     pub fn quiesce(
         &mut self,
         mut alpha: i32,
         beta: i32,
         is_white: bool,
         searched_depth: u8,
+        include_quiet_checks: bool,
     ) -> i32 {
-        match self.game.state {
-            GameState::InProgress => {}
-            GameState::WhiteWon | GameState::BlackWon => {
-                return -(CHECKMATE_SCORE - searched_depth as i32)
-            }
-            GameState::Stalemate => return DRAW_SCORE,
+        if self.is_stopped() {
+            return 0;
         }
+        self.seldepth = self.seldepth.max(searched_depth + 1);
+
         if self.game.is_threefold_repetition() {
             return DRAW_SCORE;
         }
+        // Lines end here, so that every mate score lies beyond MATE_THRESHOLD
+        if searched_depth as usize >= MAX_SEARCH_PLIES {
+            let offset = if is_white { -1 } else { 1 };
+            return offset * evaluate_board(self.game.current_board());
+        }
+
+        let zobrist = self.game.get_current_zobrist();
+        let tt_entry = self.tt.get_entry(zobrist);
+        let hash_move = if tt_entry.is_valid(zobrist) {
+            let tt_search_data = tt_entry.get_search_data();
+            let tt_score = score_from_tt(tt_search_data.score(), searched_depth as i32);
+            // Every entry is at least as deep as the quiescence search
+            if !self.mate_search {
+                match tt_search_data.node_type() {
+                    NodeType::PV => {
+                        self.tt.record_cutoff();
+                        return tt_score;
+                    }
+                    NodeType::Cut => {
+                        if tt_score >= beta {
+                            self.tt.record_cutoff();
+                            return beta;
+                        }
+                    }
+                    NodeType::All => {
+                        if tt_score <= alpha {
+                            self.tt.record_cutoff();
+                            return alpha;
+                        }
+                    }
+                }
+            }
+            self.get_hash_move(&tt_entry)
+        } else {
+            None
+        };
+        // Results of the quiescence search must not replace those of deeper searches
+        let may_store_in_tt =
+            !tt_entry.is_valid(zobrist) || tt_entry.get_search_data().depth() == 0;
+
+        let in_check = self.game.is_in_check();
+        let original_alpha = alpha;
+
+        // The side to move may not stand pat if in check, since it might be mated
+        if !in_check {
+            // Standing pat in a stalemate would hide the draw
+            if !has_legal_move(self.game.current_board()) {
+                return DRAW_SCORE;
+            }
 
-        let offset = if is_white { -1 } else { 1 };
-        let stand_pat = offset * evaluate_board(self.game.current_board());
+            let offset = if is_white { -1 } else { 1 };
+            let stand_pat = offset * evaluate_board(self.game.current_board());
 
-        let in_check = self.game.current_board().is_in_check();
+            if stand_pat >= beta {
+                if may_store_in_tt {
+                    self.tt.set_entry(
+                        zobrist,
+                        build_tt_entry(
+                            None,
+                            zobrist,
+                            0,
+                            stand_pat,
+                            searched_depth as i32,
+                            NodeType::Cut,
+                        ),
+                    );
+                }
+                return beta;
+            }
 
-        // Do not return stand-pat if in check
-        if stand_pat >= beta && !in_check {
-            return beta;
-        }
+            // Delta pruning
+            if stand_pat < alpha - DELTA_PRUNING_THRESHOLD {
+                // If giving a side a queen is not good enough,
+                // then we conclude that further searches are futile
+                return alpha;
+            }
 
-        // Delta pruning
-        if stand_pat < alpha - DELTA_PRUNING_THRESHOLD {
-            // If giving a side a queen is not good enough,
-            // then we conclude that further searches are futile
-            return alpha;
+            if alpha < stand_pat {
+                alpha = stand_pat;
+            }
         }
 
-        if alpha < stand_pat {
-            alpha = stand_pat;
+        // The hash move is searched first if it is one of the moves of the quiescence
+        // search, the other moves are sorted by SEE
+        let board = self.game.current_board();
+        let moves = if in_check {
+            generate_legal_moves(board)
+        } else {
+            let mut moves: Vec<Move> = generate_legal_captures(board)
+                .into_iter()
+                .filter(|m| m.is_capture)
+                .collect();
+            if include_quiet_checks {
+                moves.extend(
+                    generate_legal_quiet_moves(board)
+                        .into_iter()
+                        .filter(|m| gives_check(board, m)),
+                );
+            }
+            moves
+        };
+        // Positions are not checked for a mate when they are reached, see alpha_beta
+        if in_check && moves.is_empty() {
+            return -(CHECKMATE_SCORE - searched_depth as i32);
         }
+        let mut moves = moves
+            .iter()
+            .map(|m| {
+                let score = if hash_move == Some(*m) {
+                    i32::MAX
+                } else {
+                    static_exchange_evaluation_capture(board, m)
+                };
+                (*m, score)
+            })
+            .collect::<Vec<(Move, i32)>>();
+        moves.sort_by_key(|(_, score)| Reverse(*score));
 
-        // Sort moves based on SEE
-        let mut non_quiescent_moves = filter_non_quiescent_moves(
-            self.game.current_board(),
-            self.game.current_legal_moves().clone(),
-        )
-        .into_iter()
-        .map(|m| {
-            (
-                m,
-                static_exchange_evaluation_capture(&self.game.current_board(), &m),
-            )
-        })
-        .collect::<Vec<(Move, i32)>>();
-        non_quiescent_moves.sort_by(|(_, see1), (_, see2)| see2.cmp(see1));
-
-        for (m, see) in non_quiescent_moves {
+        let mut best_move = None;
+        for (m, see) in moves {
             // Prune captures with SEE < 0
             if see < 0 && !in_check {
                 break;
             }
-            self.nodes_searched += 1;
+            self.nodes_searched.fetch_add(1, Ordering::Relaxed);
+
+            self.game.make_move(&m);
+            let score = -self.quiesce(-beta, -alpha, !is_white, searched_depth + 1, false);
+            self.game.unmake_move();
 
-            self.game.apply_move(&m);
-            let score = -self.quiesce(-beta, -alpha, !is_white, searched_depth + 1);
-            self.game.undo_move();
+            if self.is_stopped() {
+                return 0;
+            }
 
             if score >= beta {
-                // TODO: Should this be stored?
-                self.store_killer_move(m, searched_depth as u8);
+                if may_store_in_tt {
+                    self.tt.set_entry(
+                        zobrist,
+                        build_tt_entry(
+                            Some(&m),
+                            zobrist,
+                            0,
+                            score,
+                            searched_depth as i32,
+                            NodeType::Cut,
+                        ),
+                    );
+                }
                 return beta;
             }
 
             if score > alpha {
+                best_move = Some(m);
                 alpha = score;
             }
         }
 
-        return alpha;
+        if may_store_in_tt {
+            self.tt.set_entry(
+                zobrist,
+                build_tt_entry(
+                    best_move.as_ref(),
+                    zobrist,
+                    0,
+                    alpha,
+                    searched_depth as i32,
+                    if alpha > original_alpha {
+                        NodeType::PV
+                    } else {
+                        NodeType::All
+                    },
+                ),
+            );
+        }
+
+        alpha
     }
 
-    pub fn apply_best_move(&mut self) {
-        match self.get_best_move(None) {
-            Ok(m) => {
-                self.game.apply_move(&m);
+    pub fn apply_best_move(&mut self) -> SearchResult {
+        match self.search(&SearchLimits::new_with_depth(self.search_depth.into())) {
+            Ok(result) => {
+                self.game.apply_move(&result.best_move);
+                result
             }
             Err(e) => panic!("Unable to apply best move. Error: {}", e),
         }
     }
 
-    pub fn get_nodes_searched(&self) -> u32 {
-        self.nodes_searched
+    pub fn get_nodes_searched(&self) -> u64 {
+        self.nodes_searched.load(Ordering::Relaxed)
     }
 
     pub fn may_do_null_move_pruning(&self, remaining_depth: u8, is_white: bool) -> bool {
@@ -467,38 +1470,109 @@ impl Searcher {
             .get_non_king_pawn_bb_for_color(color)
             != 0
             && !self.game.is_in_check()
-            && remaining_depth >= NULL_MOVE_PRUNING_R
+            && remaining_depth > NULL_MOVE_PRUNING_R
+    }
+
+    // Extensions are limited so that lines are at most twice as long
+    // as the depth of the iteration and never reach MAX_SEARCH_PLIES
+    fn may_extend(&self, remaining_depth: u8, searched_depth: u8) -> bool {
+        (searched_depth as u32) < 2 * self.info.root_depth as u32
+            && searched_depth as usize + remaining_depth as usize + 1 < MAX_SEARCH_PLIES
+    }
+
+    // Whether a capture that wins enough material according to SEE beats probcut_beta
+    // in a quiescence search and then in a search that is reduced by a few plies
+    fn probcut(
+        &mut self,
+        probcut_beta: i32,
+        static_eval: i32,
+        remaining_depth: u8,
+        is_white: bool,
+        searched_depth: u8,
+    ) -> bool {
+        let board = self.game.current_board();
+        let captures: Vec<Move> = generate_legal_captures(board)
+            .into_iter()
+            .filter(|m| {
+                m.is_capture
+                    && static_exchange_evaluation_capture(board, m) >= probcut_beta - static_eval
+            })
+            .collect();
+
+        // The moves of ProbCut are never part of the PV
+        let follow_pv = self.info.follow_pv;
+        self.info.follow_pv = false;
+        let mut beats_probcut_beta = false;
+        for m in captures {
+            self.nodes_searched.fetch_add(1, Ordering::Relaxed);
+            self.info.prev_moves[searched_depth as usize + 1] = Some(m);
+            self.game.make_move(&m);
+            let mut score = -self.quiesce(
+                -probcut_beta,
+                -probcut_beta + 1,
+                !is_white,
+                searched_depth + 1,
+                false,
+            );
+            if score >= probcut_beta {
+                score = -self.alpha_beta(
+                    remaining_depth - PROBCUT_DEPTH_REDUCTION,
+                    -probcut_beta,
+                    -probcut_beta + 1,
+                    !is_white,
+                    false,
+                    searched_depth + 1,
+                );
+            }
+            self.game.unmake_move();
+
+            if self.is_stopped() {
+                break;
+            }
+            if score >= probcut_beta {
+                beats_probcut_beta = true;
+                break;
+            }
+        }
+        self.info.follow_pv = follow_pv;
+        beats_probcut_beta
+    }
+
+    // Whether every move other than the hash move fails low against singular_beta
+    // in a reduced search, i.e. whether the hash move is clearly the best move
+    fn is_singular_move(
+        &mut self,
+        hash_move: Move,
+        singular_beta: i32,
+        remaining_depth: u8,
+        is_white: bool,
+        can_prune: bool,
+        searched_depth: u8,
+    ) -> bool {
+        let follow_pv = self.info.follow_pv;
+        self.info.follow_pv = false;
+        self.info.excluded_moves[searched_depth as usize] = Some(hash_move);
+        let score = self.alpha_beta(
+            (remaining_depth - 1) / 2,
+            singular_beta - 1,
+            singular_beta,
+            is_white,
+            can_prune,
+            searched_depth,
+        );
+        self.info.excluded_moves[searched_depth as usize] = None;
+        self.info.follow_pv = follow_pv;
+        score < singular_beta
     }
 
-    // This implementation relies on there being only 2 killer moves
-    // per ply. If NUM_KILLER_MOVES is changed, this implementation
-    // will also have to be changed.
+    // The newest killer move takes the first slot, and
+    // the other killer moves are shifted to the next slots
     fn store_killer_move(&mut self, m: Move, curr_ply: u8) {
         if (curr_ply as usize) < MAX_SEARCH_PLIES {
-            match (
-                self.info.killer_moves[curr_ply as usize][0],
-                self.info.killer_moves[curr_ply as usize][1],
-            ) {
-                // If both slots are already occupied, too bad we don't
-                // overwrite any of them
-                // TODO: Investigate this! Is it helpful to do some
-                // form of eviction or reordering?
-                (Some(_), Some(_)) => (),
-                // If the current move is already saved, we ignore it.
-                // Otherwise, we put the current move in the first slot
-                // and shift the previous move to the next slot.
-                (Some(saved_move), None) => {
-                    if saved_move == m {
-                        self.info.killer_moves[curr_ply as usize][0] = Some(m);
-                        self.info.killer_moves[curr_ply as usize][1] = Some(saved_move)
-                    }
-                }
-                // If no moves have been saved yet, just save the
-                // given move.
-                // Note: based on how the other branch works, if the
-                // first slot is empty, we can safely assume that the
-                // second one is also empty.
-                (None, _) => self.info.killer_moves[curr_ply as usize][0] = Some(m),
+            let killer_moves = &mut self.info.killer_moves[curr_ply as usize];
+            if killer_moves[0] != Some(m) {
+                killer_moves.copy_within(0..NUM_KILLER_MOVES - 1, 1);
+                killer_moves[0] = Some(m);
             }
         }
     }
@@ -530,17 +1604,226 @@ impl Searcher {
 // only 2 killer moves.
 type KillerMoves = [[Option<Move>; NUM_KILLER_MOVES]; MAX_SEARCH_PLIES];
 
+// Butterfly history, indexed by the color of the moving
+// piece, the source square and the destination square
+type ButterflyHistory = [[[i32; NUM_SQUARES]; NUM_SQUARES]; 2];
+
+// Indexed by the piece and destination square of the previous move,
+// and then by the piece and destination square of the current move
+type ContinuationHistory = [[[[i32; NUM_SQUARES]; NUM_PIECES]; NUM_SQUARES]];
+
+// Triangular PV table, the PV of the node at ply `n` is stored in
+// row `n` and is built from the move searched at that node followed
+// by the PV in row `n + 1`.
+// Note: Nodes are searched up to a ply of MAX_SEARCH_PLIES (inclusive).
+type PVTable = [[Option<Move>; MAX_SEARCH_PLIES + 1]; MAX_SEARCH_PLIES + 1];
+
 #[derive(Clone)]
 struct SearchInfo {
     killer_moves: KillerMoves,
+    // Scores of quiet moves by how often they caused beta cutoffs
+    history: Box<ButterflyHistory>,
+    continuation_history: Box<ContinuationHistory>,
+    // Quiet moves that caused a beta cutoff in reply to a move,
+    // indexed by the piece and destination square of that move
+    countermoves: [[Option<Move>; NUM_SQUARES]; NUM_PIECES],
+    // Move that led to the node at each ply, this is
+    // None for null moves
+    prev_moves: [Option<Move>; MAX_SEARCH_PLIES + 1],
+    pv_table: Box<PVTable>,
+    pv_length: [usize; MAX_SEARCH_PLIES + 1],
+    // PV found by the previous iteration of iterative deepening,
+    // starting with the root move
+    prev_pv: Vec<Move>,
+    // Whether the current node lies on the previous PV
+    follow_pv: bool,
+    // Depth of the current iteration, this bounds the extensions of every line
+    root_depth: u8,
+    // Move that is skipped at each ply while checking whether the hash move is singular
+    excluded_moves: [Option<Move>; MAX_SEARCH_PLIES],
+    // Set while a null move cutoff is verified, no null moves are made meanwhile
+    verifying_null_move: bool,
 }
 
 impl SearchInfo {
     pub fn new() -> Self {
         Self {
             killer_moves: [[None; NUM_KILLER_MOVES]; MAX_SEARCH_PLIES],
+            history: Box::new([[[0; NUM_SQUARES]; NUM_SQUARES]; 2]),
+            continuation_history: vec![[[[0; NUM_SQUARES]; NUM_PIECES]; NUM_SQUARES]; NUM_PIECES]
+                .into_boxed_slice(),
+            countermoves: [[None; NUM_SQUARES]; NUM_PIECES],
+            prev_moves: [None; MAX_SEARCH_PLIES + 1],
+            pv_table: Box::new([[None; MAX_SEARCH_PLIES + 1]; MAX_SEARCH_PLIES + 1]),
+            pv_length: [0; MAX_SEARCH_PLIES + 1],
+            prev_pv: vec![],
+            follow_pv: false,
+            root_depth: 0,
+            excluded_moves: [None; MAX_SEARCH_PLIES],
+            verifying_null_move: false,
+        }
+    }
+
+    fn get_countermove(&self, prev_move: &Move) -> Option<Move> {
+        self.countermoves[piece_index(&prev_move.piece)][prev_move.dest as usize]
+    }
+
+    // Sum of the butterfly history and the continuation history of a quiet move
+    fn get_history_score(&self, m: &Move, prev_move: Option<&Move>) -> i32 {
+        let history = self.history[m.piece.color as usize][m.src as usize][m.dest as usize];
+        let continuation_history = prev_move.map_or(0, |prev_move| {
+            self.continuation_history[piece_index(&prev_move.piece)][prev_move.dest as usize]
+                [piece_index(&m.piece)][m.dest as usize]
+        });
+        history + continuation_history
+    }
+
+    // Rewards the quiet move that caused a beta cutoff, and penalises
+    // the quiet moves that were searched before it without causing one
+    fn update_quiet_move_history(
+        &mut self,
+        cutoff_move: &Move,
+        searched_quiet_moves: &[Move],
+        prev_move: Option<&Move>,
+        remaining_depth: u8,
+    ) {
+        let bonus = history_bonus(remaining_depth);
+        self.update_history_scores(cutoff_move, prev_move, bonus);
+        for m in searched_quiet_moves {
+            self.update_history_scores(m, prev_move, -bonus);
+        }
+
+        if let Some(prev_move) = prev_move {
+            self.countermoves[piece_index(&prev_move.piece)][prev_move.dest as usize] =
+                Some(*cutoff_move);
         }
     }
+
+    fn update_history_scores(&mut self, m: &Move, prev_move: Option<&Move>, bonus: i32) {
+        apply_history_bonus(
+            &mut self.history[m.piece.color as usize][m.src as usize][m.dest as usize],
+            bonus,
+        );
+        if let Some(prev_move) = prev_move {
+            apply_history_bonus(
+                &mut self.continuation_history[piece_index(&prev_move.piece)]
+                    [prev_move.dest as usize][piece_index(&m.piece)][m.dest as usize],
+                bonus,
+            );
+        }
+    }
+
+    fn update_pv(&mut self, m: Move, ply: u8) {
+        let ply = ply as usize;
+        self.pv_table[ply][0] = Some(m);
+        // A node at the maximum ply never has a child PV
+        let child_pv_length = if ply < MAX_SEARCH_PLIES {
+            self.pv_length[ply + 1]
+        } else {
+            0
+        };
+        for i in 0..child_pv_length {
+            self.pv_table[ply][i + 1] = self.pv_table[ply + 1][i];
+        }
+        self.pv_length[ply] = child_pv_length + 1;
+    }
+
+    fn get_pv(&self, ply: u8) -> Vec<Move> {
+        let ply = ply as usize;
+        self.pv_table[ply][..self.pv_length[ply]]
+            .iter()
+            .map(|m| m.expect("Missing move in PV"))
+            .collect()
+    }
+
+    // The root move is not searched by `alpha_beta`, so the node at ply `n`
+    // corresponds to entry `n + 1` of the previous PV.
+    fn get_prev_pv_move(&self, ply: u8) -> Option<Move> {
+        self.prev_pv.get(ply as usize + 1).copied()
+    }
+}
+
+fn piece_index(piece: &Piece) -> usize {
+    piece.color as usize * 6 + piece.piece_type as usize
+}
+
+// Moves that cause cutoffs closer to the root get larger bonuses
+fn history_bonus(remaining_depth: u8) -> i32 {
+    (32 * remaining_depth as i32 * remaining_depth as i32).min(HISTORY_MAX / 8)
+}
+
+// The bonus shrinks as the score approaches its bound, so the scores never leave
+// [-HISTORY_MAX, HISTORY_MAX] and older results gradually lose their weight
+fn apply_history_bonus(score: &mut i32, bonus: i32) {
+    *score += bonus - *score * bonus.abs() / HISTORY_MAX;
+}
+
+// Whether a move is extended because of what it does, recaptures are extended
+// as they only restore the material balance and pawn pushes to the seventh rank
+// are extended as the promotion that they threaten may lie beyond the horizon
+fn is_extended_move(m: &Move, prev_move: Option<&Move>, params: &SearchParams) -> bool {
+    let is_recapture =
+        m.is_capture && prev_move.is_some_and(|prev| prev.is_capture && prev.dest == m.dest);
+    let is_pawn_push_to_seventh = m.piece.piece_type == PieceType::Pawn
+        && relative_rank(m.dest.get_rank(), m.piece.color) == 7;
+    (params.recapture_extension && is_recapture)
+        || (params.passed_pawn_extension && is_pawn_push_to_seventh)
+}
+
+// Plies by which the search after a null move is reduced, deeper searches and positions
+// that are far ahead of beta are reduced further. At least one ply of the search remains.
+fn null_move_reduction(remaining_depth: u8, eval_above_beta: i32) -> u8 {
+    let eval_reduction = (eval_above_beta / NULL_MOVE_EVAL_MARGIN).clamp(0, 2) as u8;
+    let reduction = NULL_MOVE_PRUNING_R + remaining_depth / 4 + eval_reduction;
+    reduction.min(remaining_depth - 1)
+}
+
+// Number of plies by which a late move is reduced, at least one ply of the search remains
+fn late_move_reduction(remaining_depth: u8, move_idx: usize) -> u8 {
+    let reduction = LATE_MOVE_REDUCTIONS[(remaining_depth as usize).min(LMR_TABLE_SIZE - 1)]
+        [move_idx.min(LMR_TABLE_SIZE - 1)];
+    reduction.min(remaining_depth - 2)
+}
+
+// Converts the score of a root move to a score that can be reported.
+// Positions after root moves are searched with a searched depth of 0,
+// so a mate delivered by the root move itself scores CHECKMATE_SCORE.
+fn to_search_score(score: i32) -> SearchScore {
+    if score.abs() > MATE_THRESHOLD {
+        let plies_to_mate = CHECKMATE_SCORE - score.abs() + 1;
+        if score > 0 {
+            SearchScore::Mate((plies_to_mate + 1) / 2)
+        } else {
+            SearchScore::Mate(-plies_to_mate / 2)
+        }
+    } else {
+        SearchScore::Centipawns(
+            score * 100 / get_raw_piece_value(PieceType::Pawn).get_for_phase(Phase::Endgame),
+        )
+    }
+}
+
+// Mate scores are relative to the root, whereas the TT stores them relative
+// to the position of the entry. This keeps them correct when the position
+// is reached again at a different ply.
+fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score > MATE_THRESHOLD {
+        score + ply
+    } else if score < -MATE_THRESHOLD {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score > MATE_THRESHOLD {
+        score - ply
+    } else if score < -MATE_THRESHOLD {
+        score + ply
+    } else {
+        score
+    }
 }
 
 fn build_tt_entry(
@@ -548,6 +1831,7 @@ fn build_tt_entry(
     key: u64,
     depth: u8,
     score: i32,
+    ply: i32,
     node_type: NodeType,
 ) -> TranspositionTableEntry {
     let tt_move_data = match candidate_move {
@@ -560,7 +1844,7 @@ fn build_tt_entry(
 
     let mut tt_search_data = TranspositionTableEntrySearchData(0);
     tt_search_data.set_depth(depth);
-    tt_search_data.set_score(score);
+    tt_search_data.set_score(score_to_tt(score, ply));
     tt_search_data.set_node_type(node_type as u8);
     TranspositionTableEntry::new(key, tt_move_data, tt_search_data)
 }
@@ -568,7 +1852,8 @@ fn build_tt_entry(
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::board::Board;
+    use crate::board::{Board, Coordinate};
+    use crate::parser::parse_fen;
 
     #[test]
     fn test_tt_filled_at_root_depth() {
@@ -591,4 +1876,520 @@ mod test {
         assert_eq!(tt_move_data.best_move_src(), best_move.src);
         assert_eq!(tt_move_data.best_move_dest(), best_move.dest);
     }
+
+    #[test]
+    fn test_tt_mate_scores_are_relative_to_position() {
+        // Mated 3 plies after a position at ply 5
+        let score = -(CHECKMATE_SCORE - 8);
+        let entry = build_tt_entry(None, 1, 3, score, 5, NodeType::PV);
+
+        let tt_score = entry.get_search_data().score();
+        assert_eq!(tt_score, -(CHECKMATE_SCORE - 3));
+        // Reached again at ply 1, the mate is still 3 plies away
+        assert_eq!(score_from_tt(tt_score, 1), -(CHECKMATE_SCORE - 4));
+        assert_eq!(score_from_tt(tt_score, 5), score);
+
+        // Scores that are not mate scores are stored as they are
+        let entry = build_tt_entry(None, 1, 3, 250, 5, NodeType::PV);
+        assert_eq!(score_from_tt(entry.get_search_data().score(), 1), 250);
+    }
+
+    #[test]
+    fn test_transposed_mate_position_at_different_plies() {
+        let board =
+            parse_fen("r1bq3r/ppp1nQ2/2kp1N2/2b3n1/4P3/8/P2N1PPP/1RR3K1 w - - 0 1".into()).unwrap();
+        let mut fresh_searcher = Searcher::new(Game::new(board.clone()), 3, 1);
+        let mut searcher = Searcher::new(Game::new(board), 3, 1);
+
+        // Black just moved, so it is white that delivers the mate in 2
+        let expected_score =
+            fresh_searcher.alpha_beta(3, INITIAL_ALPHA, INITIAL_BETA, false, false, 0);
+        assert_eq!(to_search_score(expected_score), SearchScore::Mate(2));
+
+        // Fill the TT as if the position was reached deeper in the tree
+        let deeper_score = searcher.alpha_beta(3, INITIAL_ALPHA, INITIAL_BETA, false, false, 4);
+        assert_eq!(deeper_score, expected_score - 4);
+
+        // The TT entry of the deeper position must not change the mate distance
+        let score = searcher.alpha_beta(3, INITIAL_ALPHA, INITIAL_BETA, false, false, 0);
+        assert_eq!(score, expected_score);
+    }
+
+    #[test]
+    fn test_search_updates_tt_stats() {
+        let game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game, 4, 4);
+        // Nothing is counted until the counters are enabled
+        searcher.search(&SearchLimits::new_with_depth(2)).unwrap();
+        assert_eq!(searcher.get_tt_stats(), TranspositionTableStats::default());
+
+        searcher.set_tt_stats_enabled(true);
+        searcher.search(&SearchLimits::new_with_depth(4)).unwrap();
+
+        let stats = searcher.get_tt_stats();
+        assert!(stats.hits > 0);
+        assert!(stats.misses > 0);
+        assert!(stats.hits >= stats.cutoffs);
+    }
+
+    #[test]
+    fn test_aspiration_search_widens_window() {
+        let game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game.clone(), 4, 1);
+        let mut full_window_searcher = Searcher::new(game, 4, 1);
+        let root_moves = searcher.game.current_legal_moves().clone();
+
+        // The score of the previous iteration is far off, so the search fails low
+        let results = searcher
+            .aspiration_search(&root_moves, ASPIRATION_MIN_DEPTH, Some(5000))
+            .unwrap();
+        let full_window_results = full_window_searcher
+            .aspiration_search(&root_moves, ASPIRATION_MIN_DEPTH, None)
+            .unwrap();
+
+        assert_eq!(results.len(), root_moves.len());
+        assert_eq!(results[0].score, full_window_results[0].score);
+    }
+
+    #[test]
+    fn test_quiesce_with_quiet_checks() {
+        // Ra8 is a quiet move that mates
+        let board = parse_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".into()).unwrap();
+        let zobrist = board.get_zobrist();
+
+        let mut searcher = Searcher::new(Game::new(board.clone()), 1, 1);
+        let score = searcher.quiesce(INITIAL_ALPHA, INITIAL_BETA, false, 0, true);
+        assert_eq!(to_search_score(score), SearchScore::Mate(1));
+        // The result is stored in the TT for later quiescence searches
+        let tt_entry = searcher.tt.get_entry(zobrist);
+        assert!(tt_entry.is_valid(zobrist));
+        assert_eq!(tt_entry.get_search_data().depth(), 0);
+
+        // Only captures are searched otherwise
+        let mut searcher = Searcher::new(Game::new(board), 1, 1);
+        let score = searcher.quiesce(INITIAL_ALPHA, INITIAL_BETA, false, 0, false);
+        assert!(score.abs() < MATE_THRESHOLD);
+    }
+
+    #[test]
+    fn test_quiesce_at_max_ply() {
+        // Ra8 mates, but no moves are searched at the maximum ply
+        let board = parse_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".into()).unwrap();
+        let mut searcher = Searcher::new(Game::new(board), 1, 1);
+        let score = searcher.quiesce(
+            INITIAL_ALPHA,
+            INITIAL_BETA,
+            false,
+            MAX_SEARCH_PLIES as u8,
+            true,
+        );
+        assert_eq!(score, evaluate_board(searcher.game.current_board()));
+    }
+
+    #[test]
+    fn test_null_move_reduction() {
+        assert_eq!(null_move_reduction(4, 0), NULL_MOVE_PRUNING_R + 1);
+        // Deeper searches and larger leads over beta are reduced further
+        assert!(null_move_reduction(12, 0) > null_move_reduction(4, 0));
+        assert!(null_move_reduction(8, 1000) > null_move_reduction(8, 0));
+        // At least one ply of the search remains
+        assert_eq!(null_move_reduction(3, 10000), 2);
+    }
+
+    #[test]
+    fn test_stalemating_move_scores_draw() {
+        // Qc7 stalemates black despite the extra queen
+        let board = parse_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1".into()).unwrap();
+        let mut game = Game::new(board);
+        game.apply_move_with_src_dest(Coordinate::C1, Coordinate::C7, None)
+            .unwrap();
+        let mut searcher = Searcher::new(game, 1, 1);
+
+        // The move is searched at depth 1
+        let score = searcher.alpha_beta(0, INITIAL_ALPHA, INITIAL_BETA, true, true, 0);
+        assert_eq!(score, DRAW_SCORE);
+        let score = searcher.quiesce(INITIAL_ALPHA, INITIAL_BETA, true, 1, false);
+        assert_eq!(score, DRAW_SCORE);
+    }
+
+    #[test]
+    fn test_stalemate_after_null_move() {
+        // Black is stalemated if white passes
+        let board = parse_fen("k7/2Q5/1K6/8/8/8/8/8 w - - 0 1".into()).unwrap();
+        let mut game = Game::new(board);
+        game.apply_null_move();
+        let mut searcher = Searcher::new(game, 2, 1);
+
+        let score = searcher.alpha_beta(2, INITIAL_ALPHA, INITIAL_BETA, true, true, 1);
+        assert_eq!(score, DRAW_SCORE);
+    }
+
+    #[test]
+    fn test_late_move_reduction() {
+        assert_eq!(late_move_reduction(LMR_MIN_DEPTH, LMR_MIN_MOVES), 1);
+        // Later moves at greater depths are reduced further
+        assert!(late_move_reduction(10, 30) > late_move_reduction(10, 5));
+        assert!(late_move_reduction(10, 30) > late_move_reduction(4, 30));
+        // At least one ply of the search remains
+        assert_eq!(late_move_reduction(3, 200), 1);
+        assert!(late_move_reduction(u8::MAX, 200) < u8::MAX - 1);
+    }
+
+    #[test]
+    fn test_extended_moves() {
+        let params = SearchParams::default();
+        let white_pawn = Piece::new(Color::White, PieceType::Pawn);
+        let black_pawn = Piece::new(Color::Black, PieceType::Pawn);
+        let black_bishop = Piece::new(Color::Black, PieceType::Bishop);
+
+        // Pawn pushes to the seventh rank of the side that moves
+        let white_push = Move::new(Coordinate::B6, Coordinate::B7, white_pawn);
+        let black_push = Move::new(Coordinate::G3, Coordinate::G2, black_pawn);
+        assert!(is_extended_move(&white_push, None, &params));
+        assert!(is_extended_move(&black_push, None, &params));
+        let push = Move::new(Coordinate::B3, Coordinate::B4, white_pawn);
+        assert!(!is_extended_move(&push, None, &params));
+
+        // Captures on the square of the previous capture
+        let capture = Move::new_capture(
+            Coordinate::B7,
+            Coordinate::E4,
+            black_bishop,
+            PieceType::Knight,
+        );
+        let recapture = Move::new_capture(
+            Coordinate::D3,
+            Coordinate::E4,
+            white_pawn,
+            PieceType::Bishop,
+        );
+        assert!(is_extended_move(&recapture, Some(&capture), &params));
+        assert!(!is_extended_move(&recapture, Some(&push), &params));
+
+        let params = SearchParams {
+            recapture_extension: false,
+            passed_pawn_extension: false,
+            ..Default::default()
+        };
+        assert!(!is_extended_move(&white_push, None, &params));
+        assert!(!is_extended_move(&recapture, Some(&capture), &params));
+    }
+
+    #[test]
+    fn test_search_deeper_than_max_depth() {
+        let game = Game::new(parse_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".into()).unwrap());
+        let mut searcher = Searcher::new(game, 1, 1);
+        let limits = SearchLimits {
+            nodes: Some(50000),
+            ..SearchLimits::new_with_depth(1000)
+        };
+        let result = searcher.search(&limits).unwrap();
+        assert_eq!(result.best_move.dest, Coordinate::A8);
+    }
+
+    #[test]
+    fn test_extensions_are_bounded() {
+        let mut searcher = Searcher::new(Game::new(Board::new_starting_pos()), 1, 1);
+        searcher.info.root_depth = 4;
+        assert!(searcher.may_extend(3, 1));
+        // Lines may not become more than twice as long as the iteration's depth
+        assert!(!searcher.may_extend(1, 8));
+
+        searcher.info.root_depth = 100;
+        assert!(searcher.may_extend(10, 10));
+        assert!(searcher.may_extend(10, (MAX_SEARCH_PLIES - 12) as u8));
+        assert!(!searcher.may_extend(10, (MAX_SEARCH_PLIES - 11) as u8));
+
+        searcher.info.root_depth = MAX_SEARCH_DEPTH;
+        assert!(!searcher.may_extend(MAX_SEARCH_DEPTH, MAX_SEARCH_DEPTH));
+    }
+
+    #[test]
+    fn test_singular_move() {
+        // Kh7 is the only legal move
+        let game = Game::new(parse_fen("7k/8/8/8/8/8/8/K5R1 b - - 0 1".into()).unwrap());
+        let only_move = game.current_legal_moves()[0];
+        let zobrist = game.get_current_zobrist();
+        let mut searcher = Searcher::new(game, 1, 1);
+        assert!(searcher.is_singular_move(only_move, 0, 6, true, true, 0));
+        assert_eq!(searcher.info.excluded_moves[0], None);
+        // The search without the excluded move does not store its result
+        assert!(!searcher.tt.get_entry(zobrist).is_valid(zobrist));
+
+        // Many moves are about as good as e2e4
+        let game = Game::new(Board::new_starting_pos());
+        let m = find_legal_move(game.current_board(), Coordinate::E2, Coordinate::E4, None);
+        let mut searcher = Searcher::new(game, 1, 1);
+        assert!(!searcher.is_singular_move(m.unwrap(), -200, 6, false, true, 0));
+    }
+
+    #[test]
+    fn test_reverse_futility_pruning() {
+        // White is a queen up
+        let board = parse_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1".into()).unwrap();
+
+        let mut searcher = Searcher::new(Game::new(board.clone()), 3, 1);
+        assert_eq!(searcher.alpha_beta(3, 0, 1, false, true, 0), 1);
+        // The node is pruned before any move is searched
+        assert_eq!(searcher.get_nodes_searched(), 0);
+
+        // Nodes on the PV are searched in full
+        let mut searcher = Searcher::new(Game::new(board.clone()), 3, 1);
+        searcher.alpha_beta(3, 0, 100, false, true, 0);
+        assert!(searcher.get_nodes_searched() > 0);
+
+        // The margin can be raised until the node is no longer pruned
+        let mut searcher = Searcher::new(Game::new(board), 3, 1);
+        searcher.set_search_params(SearchParams {
+            reverse_futility_margin: 5000,
+            ..Default::default()
+        });
+        searcher.alpha_beta(3, 0, 1, false, true, 0);
+        assert!(searcher.get_nodes_searched() > 0);
+    }
+
+    #[test]
+    fn test_store_killer_move_keeps_newest_moves() {
+        let mut searcher = Searcher::new(Game::new(Board::new_starting_pos()), 1, 1);
+        let knight = Piece::new(Color::White, PieceType::Knight);
+        let move_1 = Move::new(Coordinate::B1, Coordinate::C3, knight);
+        let move_2 = Move::new(Coordinate::G1, Coordinate::F3, knight);
+        let move_3 = Move::new(Coordinate::B1, Coordinate::A3, knight);
+
+        searcher.store_killer_move(move_1, 2);
+        searcher.store_killer_move(move_2, 2);
+        searcher.store_killer_move(move_3, 2);
+        assert_eq!(searcher.info.killer_moves[2], [Some(move_3), Some(move_2)]);
+
+        // Storing a killer move again does not evict the other one
+        searcher.store_killer_move(move_3, 2);
+        assert_eq!(searcher.info.killer_moves[2], [Some(move_3), Some(move_2)]);
+    }
+
+    #[test]
+    fn test_quiet_move_history_rewards_cutoff_move() {
+        let mut info = SearchInfo::new();
+        let prev_move = Move::new(
+            Coordinate::E7,
+            Coordinate::E5,
+            Piece::new(Color::Black, PieceType::Pawn),
+        );
+        let knight = Piece::new(Color::White, PieceType::Knight);
+        let cutoff_move = Move::new(Coordinate::G1, Coordinate::F3, knight);
+        let searched_move = Move::new(Coordinate::B1, Coordinate::C3, knight);
+
+        info.update_quiet_move_history(&cutoff_move, &[searched_move], Some(&prev_move), 4);
+
+        assert!(info.get_history_score(&cutoff_move, Some(&prev_move)) > 0);
+        assert!(info.get_history_score(&searched_move, Some(&prev_move)) < 0);
+        // Only the butterfly history applies without a previous move
+        assert!(
+            info.get_history_score(&cutoff_move, None)
+                < info.get_history_score(&cutoff_move, Some(&prev_move))
+        );
+        assert_eq!(info.get_countermove(&prev_move), Some(cutoff_move));
+    }
+
+    #[test]
+    fn test_history_scores_stay_bounded() {
+        let mut score = 0;
+        for _ in 0..1000 {
+            apply_history_bonus(&mut score, history_bonus(u8::MAX));
+        }
+        assert!(score > 0 && score <= HISTORY_MAX);
+
+        for _ in 0..1000 {
+            apply_history_bonus(&mut score, -history_bonus(u8::MAX));
+        }
+        assert!(score < 0 && score >= -HISTORY_MAX);
+    }
+
+    #[test]
+    fn test_helper_search_shares_results_through_tt() {
+        let game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game, 3, 2);
+        let mut helper = searcher.clone();
+        let root_moves = helper.game.current_legal_moves().clone();
+        helper.helper_iterative_deepening(root_moves, 3, 1);
+        // The root position is left as it was
+        helper.game.restore_legal_moves();
+        assert_eq!(helper.game.current_legal_moves().len(), 20);
+
+        // The main thread finds the positions that the helper searched
+        let m = searcher.game.current_legal_moves()[0];
+        searcher.game.apply_move(&m);
+        let zobrist = searcher.game.get_current_zobrist();
+        assert!(searcher.tt.get_entry(zobrist).is_valid(zobrist));
+    }
+
+    #[test]
+    fn test_stopped_search_returns_legal_move() {
+        let game = Game::new(Board::new_starting_pos());
+        let legal_moves = game.current_legal_moves().clone();
+        let mut searcher = Searcher::new(game, 50, 4);
+        searcher.get_stop_signal().store(true, Ordering::SeqCst);
+
+        let best_move = searcher.get_best_move(None).unwrap();
+
+        assert!(legal_moves.contains(&best_move));
+    }
+
+    #[test]
+    fn test_stop_signal_interrupts_running_search() {
+        let game = Game::new(Board::new_starting_pos());
+        let legal_moves = game.current_legal_moves().clone();
+        let mut searcher = Searcher::new(game, 50, 4);
+        let stop_signal = searcher.get_stop_signal();
+
+        let handle = std::thread::spawn(move || searcher.get_best_move(None).unwrap());
+        std::thread::sleep(Duration::from_millis(500));
+        stop_signal.store(true, Ordering::SeqCst);
+
+        let best_move = handle.join().unwrap();
+        assert!(legal_moves.contains(&best_move));
+    }
+
+    #[test]
+    fn test_search_returns_legal_pv_starting_with_best_move() {
+        let mut game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game.clone(), 4, 4);
+
+        let result = searcher.search(&SearchLimits::new_with_depth(4)).unwrap();
+
+        assert_eq!(result.pv[0], result.best_move);
+        assert!(result.pv.len() > 1);
+        for m in &result.pv {
+            assert!(game.current_legal_moves().contains(m));
+            game.apply_move(m);
+        }
+    }
+
+    #[test]
+    fn test_search_respects_node_limit() {
+        let game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game, 5, 4);
+        let limits = SearchLimits {
+            nodes: Some(20000),
+            ..Default::default()
+        };
+
+        searcher.search(&limits).unwrap();
+
+        let nodes = searcher.get_nodes_searched();
+        assert!(nodes >= 20000);
+        // Threads may search a few extra nodes before noticing the limit
+        assert!(nodes < 21000, "{}", nodes);
+    }
+
+    #[test]
+    fn test_search_respects_movetime() {
+        let game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game, 5, 4);
+        let limits = SearchLimits {
+            movetime: Some(300),
+            ..Default::default()
+        };
+
+        let start_time = Instant::now();
+        searcher.search(&limits).unwrap();
+
+        assert!(start_time.elapsed() < Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_infinite_search_waits_for_stop_signal() {
+        let game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game, 5, 4);
+        let stop_signal = searcher.get_stop_signal();
+        let limits = SearchLimits {
+            depth: Some(1),
+            infinite: true,
+            ..Default::default()
+        };
+
+        let handle = std::thread::spawn(move || searcher.search(&limits));
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(!handle.is_finished());
+
+        stop_signal.store(true, Ordering::SeqCst);
+        assert!(handle.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_search_restricted_to_search_moves() {
+        let game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game, 3, 4);
+        let search_moves = vec![
+            (Coordinate::A2, Coordinate::A3, None),
+            (Coordinate::H2, Coordinate::H3, None),
+        ];
+        let limits = SearchLimits {
+            search_moves: Some(search_moves.clone()),
+            ..SearchLimits::new_with_depth(3)
+        };
+
+        let result = searcher.search(&limits).unwrap();
+
+        assert!(search_moves.contains(&result.best_move.to_src_dest()));
+    }
+
+    #[test]
+    fn test_search_ignores_illegal_search_moves() {
+        let game = Game::new(Board::new_starting_pos());
+        let legal_moves = game.current_legal_moves().clone();
+        let mut searcher = Searcher::new(game, 2, 4);
+        let limits = SearchLimits {
+            search_moves: Some(vec![(Coordinate::E2, Coordinate::E5, None)]),
+            ..SearchLimits::new_with_depth(2)
+        };
+
+        let result = searcher.search(&limits).unwrap();
+
+        assert!(legal_moves.contains(&result.best_move));
+    }
+
+    #[test]
+    fn test_pondering_search_waits_for_ponderhit() {
+        let game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game, 5, 4);
+        let ponder_signal = Arc::new(AtomicBool::new(true));
+        searcher.set_ponder_signal(Arc::clone(&ponder_signal));
+        let limits = SearchLimits {
+            movetime: Some(100),
+            ..Default::default()
+        };
+
+        let handle = std::thread::spawn(move || searcher.search(&limits));
+        // The time limit does not apply while pondering
+        std::thread::sleep(Duration::from_millis(500));
+        assert!(!handle.is_finished());
+
+        ponder_signal.store(false, Ordering::SeqCst);
+        let start_time = Instant::now();
+        assert!(handle.join().unwrap().is_ok());
+        assert!(start_time.elapsed() < Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_multi_pv_search_returns_sorted_lines() {
+        let game = Game::new(Board::new_starting_pos());
+        let mut searcher = Searcher::new(game, 3, 4);
+        searcher.set_multi_pv(3);
+
+        let result = searcher.search(&SearchLimits::new_with_depth(3)).unwrap();
+
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].pv, result.pv);
+        assert_eq!(result.lines[0].score, result.score);
+        // Every line starts with a different root move
+        assert_ne!(result.lines[0].pv[0], result.lines[1].pv[0]);
+        assert_ne!(result.lines[1].pv[0], result.lines[2].pv[0]);
+        assert_ne!(result.lines[0].pv[0], result.lines[2].pv[0]);
+        for line in result.lines.windows(2) {
+            match (line[0].score, line[1].score) {
+                (SearchScore::Centipawns(a), SearchScore::Centipawns(b)) => assert!(a >= b),
+                _ => panic!("Unexpected mate score in the starting position"),
+            }
+        }
+    }
 }
@@ -1,9 +1,33 @@
-use std::cell::UnsafeCell;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 
+use super::search::MAX_SEARCH_DEPTH;
 use crate::board::{Coordinate, PieceType};
+use crate::zobrist::ZOBRIST_SEED;
 
-const TT_SIZE: usize = 10_000_000;
+/// Size of the transposition table in megabytes unless configured otherwise
+pub const DEFAULT_TT_SIZE_MB: usize = 16;
+
+// Files that contain a saved table start with this
+const TT_FILE_MAGIC: [u8; 8] = *b"REQUINTT";
+// Has to be incremented whenever the layout of the file or of the entries changes
+const TT_FILE_VERSION: u32 = 1;
+
+// Number of entries that are sampled to estimate how full the table is
+const HASHFULL_SAMPLE_SIZE: usize = 1000;
+
+// Number of entries that positions with the same index compete for,
+// the last entry of every bucket is always replaced
+const BUCKET_SIZE: usize = 4;
+
+const EMPTY_ENTRY: TranspositionTableEntry = TranspositionTableEntry(
+    0,
+    TranspositionTableEntryMoveData(0),
+    TranspositionTableEntrySearchData(0),
+);
 
 #[repr(u8)]
 #[derive(Debug, PartialEq)]
@@ -63,6 +87,8 @@ bitfield! {
   pub i32, score, set_score: 31, 0;
   pub u8, depth, set_depth: 39, 32;
   pub u8, into NodeType, node_type, set_node_type: 41, 40;
+  // Generation of the search that stored the entry
+  pub u8, generation, set_generation: 49, 42;
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -96,43 +122,347 @@ impl TranspositionTableEntry {
     pub fn get_search_data(&self) -> TranspositionTableEntrySearchData {
         self.2
     }
+
+    // Returns a copy of the entry that belongs to the given generation
+    fn with_generation(&self, generation: u8) -> Self {
+        let mut search_data = self.2;
+        search_data.set_generation(generation);
+        Self::new(self.get_key(), self.1, search_data)
+    }
+}
+
+// Storage of an entry that can be shared between threads. The fields are
+// loaded and stored independently, so an entry that is written by several
+// threads at once may get torn. This is detected when the entry is
+// validated, since its key is stored XOR-ed with the other fields.
+#[derive(Debug, Default)]
+struct AtomicTranspositionTableEntry {
+    key: AtomicU64,
+    move_data: AtomicU64,
+    search_data: AtomicU64,
+}
+
+impl AtomicTranspositionTableEntry {
+    fn load(&self) -> TranspositionTableEntry {
+        TranspositionTableEntry(
+            self.key.load(Ordering::Relaxed),
+            TranspositionTableEntryMoveData(self.move_data.load(Ordering::Relaxed)),
+            TranspositionTableEntrySearchData(self.search_data.load(Ordering::Relaxed)),
+        )
+    }
+
+    fn store(&self, entry: TranspositionTableEntry) {
+        self.key.store(entry.0, Ordering::Relaxed);
+        self.move_data.store(entry.1 .0, Ordering::Relaxed);
+        self.search_data.store(entry.2 .0, Ordering::Relaxed);
+    }
+}
+
+/// Counters that show how effective the transposition table is
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TranspositionTableStats {
+    /// Probes that found an entry of the position
+    pub hits: u64,
+    /// Probes that did not find an entry of the position
+    pub misses: u64,
+    /// Stores that evicted an entry of another position from the current search
+    pub collisions: u64,
+    /// Probes whose entry was good enough to end the search of the position
+    pub cutoffs: u64,
+}
+
+// Every counter takes up a cache line of its own, so updating
+// one does not slow down the threads that read the others
+#[repr(align(64))]
+#[derive(Debug, Default)]
+struct PaddedCounter(AtomicU64);
+
+// Counting is off by default, since every probe of every thread
+// would otherwise update the same few cache lines
+#[derive(Debug, Default)]
+struct AtomicTranspositionTableStats {
+    enabled: AtomicBool,
+    hits: PaddedCounter,
+    misses: PaddedCounter,
+    collisions: PaddedCounter,
+    cutoffs: PaddedCounter,
+}
+
+impl AtomicTranspositionTableStats {
+    fn count(&self, counter: &PaddedCounter) {
+        if self.enabled.load(Ordering::Relaxed) {
+            counter.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct TranspositionTable {
-    pub data: Arc<UnsafeCell<[TranspositionTableEntry; TT_SIZE]>>,
+    // The number of entries never changes after the table is built,
+    // the entries of a bucket are stored next to each other
+    data: Arc<[AtomicTranspositionTableEntry]>,
+    // Incremented at the start of every search, entries that were
+    // stored by earlier searches are the first to be replaced
+    generation: Arc<AtomicU8>,
+    stats: Arc<AtomicTranspositionTableStats>,
+    size_mb: usize,
 }
 
-unsafe impl Sync for TranspositionTable {}
-unsafe impl Send for TranspositionTable {}
-
-pub fn build_new_tt() -> TranspositionTable {
+/// Builds a table that takes up approximately `size_mb` megabytes
+pub fn build_new_tt(size_mb: usize) -> TranspositionTable {
+    let bucket_size_bytes = BUCKET_SIZE * size_of::<AtomicTranspositionTableEntry>();
+    let num_buckets = (size_mb * 1024 * 1024 / bucket_size_bytes).max(1);
     TranspositionTable {
-        data: Arc::new(UnsafeCell::new(
-            [TranspositionTableEntry(
-                0,
-                TranspositionTableEntryMoveData(0),
-                TranspositionTableEntrySearchData(0),
-            ); TT_SIZE],
-        )),
+        data: (0..num_buckets * BUCKET_SIZE)
+            .map(|_| AtomicTranspositionTableEntry::default())
+            .collect(),
+        // Empty entries belong to generation 0
+        generation: Arc::new(AtomicU8::new(1)),
+        stats: Arc::new(AtomicTranspositionTableStats::default()),
+        size_mb,
     }
 }
 
-pub fn get_tt_index(key: u64) -> usize {
-    key as usize % TT_SIZE
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], &'static str> {
+    let mut buf = [0; N];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| "The file is truncated.")?;
+    Ok(buf)
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, &'static str> {
+    Ok(u64::from_le_bytes(read_bytes(reader)?))
+}
+
+// Whether the fields of an entry read from a file can be decoded, the raw bits
+// are checked since decoding an invalid square, piece type or node type panics
+fn is_loadable_entry(entry: &TranspositionTableEntry) -> bool {
+    let move_data = entry.1;
+    let search_data = entry.2;
+    let node_type_bits = (search_data.0 >> 40) & 0b11;
+    move_data.0 & 0xFF < 64
+        && (move_data.0 >> 8) & 0xFF < 64
+        && (move_data.0 >> 16) & 0xFF < 6
+        && node_type_bits <= NodeType::All as u64
+        && search_data.depth() <= MAX_SEARCH_DEPTH
+}
+
+pub fn get_bucket_index(key: u64, num_buckets: usize) -> usize {
+    key as usize % num_buckets
 }
 
 impl TranspositionTable {
     pub fn get_entry(&self, key: u64) -> TranspositionTableEntry {
-        let index = get_tt_index(key);
-        unsafe { (*self.data.get())[index] }
+        let generation = self.generation();
+        for atomic_entry in self.get_bucket(key) {
+            let entry = atomic_entry.load();
+            if entry.is_valid(key) {
+                // Entries that are still in use should not be replaced as if they were stale
+                if entry.get_search_data().generation() != generation {
+                    let entry = entry.with_generation(generation);
+                    atomic_entry.store(entry);
+                    self.stats.count(&self.stats.hits);
+                    return entry;
+                }
+                self.stats.count(&self.stats.hits);
+                return entry;
+            }
+        }
+        self.stats.count(&self.stats.misses);
+        EMPTY_ENTRY
     }
 
     pub fn set_entry(&self, key: u64, entry: TranspositionTableEntry) {
-        let index = get_tt_index(key);
-        unsafe {
-            (*self.data.get())[index] = entry;
+        let generation = self.generation();
+        let entry = entry.with_generation(generation);
+        let depth = entry.get_search_data().depth();
+        let atomic_bucket = self.get_bucket(key);
+        let bucket: [TranspositionTableEntry; BUCKET_SIZE] =
+            std::array::from_fn(|idx| atomic_bucket[idx].load());
+
+        // Replacement policy:
+        // 1. Entry of the same position
+        // 2. Shallowest entry from an earlier search
+        // 3. Shallowest depth-preferred entry, if it is not deeper than the new one
+        // 4. The always-replace entry
+        let index = bucket
+            .iter()
+            .position(|e| e.is_valid(key))
+            .or_else(|| {
+                bucket
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, e)| e.get_search_data().generation() != generation)
+                    .min_by_key(|(_, e)| e.get_search_data().depth())
+                    .map(|(idx, _)| idx)
+            })
+            .or_else(|| {
+                bucket[..BUCKET_SIZE - 1]
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, e)| e.get_search_data().depth())
+                    .filter(|(_, e)| e.get_search_data().depth() <= depth)
+                    .map(|(idx, _)| idx)
+            })
+            .unwrap_or(BUCKET_SIZE - 1);
+
+        let replaced_entry = bucket[index];
+        if !replaced_entry.is_valid(key)
+            && replaced_entry.get_search_data().generation() == generation
+        {
+            self.stats.count(&self.stats.collisions);
+        }
+        atomic_bucket[index].store(entry);
+    }
+
+    /// Counts a probe whose entry ended the search of a position
+    pub fn record_cutoff(&self) {
+        self.stats.count(&self.stats.cutoffs);
+    }
+
+    /// Turns the counters on or off for all clones of the table, they are off
+    /// by default since counting slows down searches with many threads
+    pub fn set_stats_enabled(&self, enabled: bool) {
+        self.stats.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns the counters since the table was built or last cleared,
+    /// only what happened while they were enabled is counted
+    pub fn get_stats(&self) -> TranspositionTableStats {
+        TranspositionTableStats {
+            hits: self.stats.hits.0.load(Ordering::Relaxed),
+            misses: self.stats.misses.0.load(Ordering::Relaxed),
+            collisions: self.stats.collisions.0.load(Ordering::Relaxed),
+            cutoffs: self.stats.cutoffs.0.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Estimates the permille of entries that are used by the
+    /// current search by sampling the start of the table
+    pub fn hashfull(&self) -> u32 {
+        let generation = self.generation();
+        let sample = &self.data[..HASHFULL_SAMPLE_SIZE.min(self.data.len())];
+        let num_used = sample
+            .iter()
+            .filter(|e| e.load().get_search_data().generation() == generation)
+            .count();
+        (num_used * 1000 / sample.len()) as u32
+    }
+
+    fn get_bucket(&self, key: u64) -> &[AtomicTranspositionTableEntry] {
+        let bucket_start = get_bucket_index(key, self.num_buckets()) * BUCKET_SIZE;
+        &self.data[bucket_start..bucket_start + BUCKET_SIZE]
+    }
+
+    /// Starts a new generation of entries, this should be called before
+    /// every search so that entries of earlier searches are replaced first
+    pub fn new_search(&self) {
+        let _ = self
+            .generation
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |generation| {
+                // Generation 0 is skipped as it is reserved for empty entries
+                Some(generation.checked_add(1).unwrap_or(1))
+            });
+    }
+
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    fn num_buckets(&self) -> usize {
+        self.num_entries() / BUCKET_SIZE
+    }
+
+    /// Removes every entry and resets the counters, this
+    /// affects all clones of the table
+    pub fn clear(&self) {
+        for entry in self.data.iter() {
+            entry.store(EMPTY_ENTRY);
+        }
+        for counter in [
+            &self.stats.hits,
+            &self.stats.misses,
+            &self.stats.collisions,
+            &self.stats.cutoffs,
+        ] {
+            counter.0.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Writes every entry that is in use to a file. Entries only depend on the
+    /// Zobrist keys, so the file can be loaded into a table of any size.
+    pub fn save(&self, path: &Path) -> Result<(), &'static str> {
+        let file = File::create(path).map_err(|_| "Unable to create the file.")?;
+        let mut writer = BufWriter::new(file);
+        let entries = self
+            .data
+            .iter()
+            .map(|e| e.load())
+            .filter(|e| e.get_search_data().generation() != 0)
+            .collect::<Vec<TranspositionTableEntry>>();
+
+        let mut write_file = || -> std::io::Result<()> {
+            // Header
+            writer.write_all(&TT_FILE_MAGIC)?;
+            writer.write_all(&TT_FILE_VERSION.to_le_bytes())?;
+            writer.write_all(&ZOBRIST_SEED.to_le_bytes())?;
+            writer.write_all(&(entries.len() as u64).to_le_bytes())?;
+
+            for entry in &entries {
+                for field in [entry.0, entry.1 .0, entry.2 .0] {
+                    writer.write_all(&field.to_le_bytes())?;
+                }
+            }
+            writer.flush()
+        };
+        write_file().map_err(|_| "Unable to write to the file.")
+    }
+
+    /// Adds the entries of a file that was written by `save` to the table,
+    /// files of other versions, with other Zobrist keys or with entries that the
+    /// engine could not have stored are rejected.
+    pub fn load(&self, path: &Path) -> Result<(), &'static str> {
+        let file = File::open(path).map_err(|_| "Unable to open the file.")?;
+        let mut reader = BufReader::new(file);
+
+        if read_bytes::<8>(&mut reader)? != TT_FILE_MAGIC {
+            return Err("The file does not contain a transposition table.");
+        }
+        if u32::from_le_bytes(read_bytes(&mut reader)?) != TT_FILE_VERSION {
+            return Err("The version of the file is not supported.");
         }
+        if read_u64(&mut reader)? != ZOBRIST_SEED {
+            return Err("The file was saved with different Zobrist keys.");
+        }
+
+        let num_entries = read_u64(&mut reader)?;
+        let mut entries = Vec::new();
+        for _ in 0..num_entries {
+            let entry = TranspositionTableEntry(
+                read_u64(&mut reader)?,
+                TranspositionTableEntryMoveData(read_u64(&mut reader)?),
+                TranspositionTableEntrySearchData(read_u64(&mut reader)?),
+            );
+            if !is_loadable_entry(&entry) {
+                return Err("The file contains an invalid entry.");
+            }
+            entries.push(entry);
+        }
+
+        // Only modify the table once the whole file is known to be valid
+        for entry in entries {
+            self.set_entry(entry.get_key(), entry);
+        }
+        Ok(())
+    }
+
+    pub fn num_entries(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn size_mb(&self) -> usize {
+        self.size_mb
     }
 }
 
@@ -150,6 +480,267 @@ mod test {
         assert!(!entry.is_valid(key + 1));
     }
 
+    #[test]
+    fn test_tt_size_depends_on_megabytes() {
+        let tt = build_new_tt(1);
+        let bucket_size_bytes = BUCKET_SIZE * size_of::<AtomicTranspositionTableEntry>();
+        assert_eq!(
+            tt.num_entries(),
+            1024 * 1024 / bucket_size_bytes * BUCKET_SIZE
+        );
+        assert_eq!(
+            build_new_tt(4).num_entries(),
+            4 * 1024 * 1024 / bucket_size_bytes * BUCKET_SIZE
+        );
+    }
+
+    #[test]
+    fn test_tt_clear() {
+        let tt = build_new_tt(1);
+        let key: u64 = 0b11110110110101101011;
+        let entry = TranspositionTableEntry::new(
+            key,
+            TranspositionTableEntryMoveData(0b1010101011110),
+            TranspositionTableEntrySearchData(0b111110110),
+        );
+        tt.set_entry(key, entry);
+        assert!(tt.get_entry(key).is_valid(key));
+
+        // Clones share their entries
+        tt.clone().clear();
+        assert!(!tt.get_entry(key).is_valid(key));
+    }
+
+    // Builds an entry at the given depth for a key
+    fn build_entry(key: u64, depth: u8) -> TranspositionTableEntry {
+        let mut search_data = TranspositionTableEntrySearchData(0);
+        search_data.set_depth(depth);
+        TranspositionTableEntry::new(key, TranspositionTableEntryMoveData(0), search_data)
+    }
+
+    // Keys that are all stored in the first bucket of the table
+    fn keys_of_same_bucket(tt: &TranspositionTable, n: usize) -> Vec<u64> {
+        (1..=n as u64)
+            .map(|i| i * tt.num_buckets() as u64)
+            .collect()
+    }
+
+    #[test]
+    fn test_tt_bucket_keeps_multiple_positions() {
+        let tt = build_new_tt(1);
+        let keys = keys_of_same_bucket(&tt, BUCKET_SIZE);
+        for &key in &keys {
+            tt.set_entry(key, build_entry(key, 3));
+        }
+
+        for &key in &keys {
+            assert!(tt.get_entry(key).is_valid(key));
+        }
+    }
+
+    #[test]
+    fn test_tt_prefers_deeper_entries() {
+        let tt = build_new_tt(1);
+        let keys = keys_of_same_bucket(&tt, BUCKET_SIZE + 2);
+        for &key in &keys[..BUCKET_SIZE] {
+            tt.set_entry(key, build_entry(key, 5));
+        }
+
+        // Shallow entries may only take the always-replace slot
+        let shallow_keys = &keys[BUCKET_SIZE..];
+        tt.set_entry(shallow_keys[0], build_entry(shallow_keys[0], 1));
+        tt.set_entry(shallow_keys[1], build_entry(shallow_keys[1], 1));
+
+        for &key in &keys[..BUCKET_SIZE - 1] {
+            assert!(tt.get_entry(key).is_valid(key));
+        }
+        assert!(!tt.get_entry(shallow_keys[0]).is_valid(shallow_keys[0]));
+        assert!(tt.get_entry(shallow_keys[1]).is_valid(shallow_keys[1]));
+    }
+
+    #[test]
+    fn test_tt_replaces_stale_entries_first() {
+        let tt = build_new_tt(1);
+        let keys = keys_of_same_bucket(&tt, BUCKET_SIZE + 1);
+        for &key in &keys[..BUCKET_SIZE] {
+            tt.set_entry(key, build_entry(key, 5));
+        }
+        tt.new_search();
+        // Probing an entry makes it part of the current search
+        assert!(tt.get_entry(keys[0]).is_valid(keys[0]));
+
+        let new_key = keys[BUCKET_SIZE];
+        tt.set_entry(new_key, build_entry(new_key, 1));
+
+        assert!(tt.get_entry(new_key).is_valid(new_key));
+        assert!(tt.get_entry(keys[0]).is_valid(keys[0]));
+        let num_remaining = keys[..BUCKET_SIZE]
+            .iter()
+            .filter(|&&key| tt.get_entry(key).is_valid(key))
+            .count();
+        assert_eq!(num_remaining, BUCKET_SIZE - 1);
+    }
+
+    #[test]
+    fn test_tt_stats() {
+        let tt = build_new_tt(1);
+        let keys = keys_of_same_bucket(&tt, BUCKET_SIZE + 1);
+        // Nothing is counted until the counters are enabled
+        tt.get_entry(keys[0]);
+        assert_eq!(tt.get_stats(), TranspositionTableStats::default());
+
+        tt.set_stats_enabled(true);
+        for &key in &keys {
+            tt.set_entry(key, build_entry(key, 3));
+        }
+        // The last entry evicted an entry of the same search
+        assert_eq!(tt.get_stats().collisions, 1);
+
+        tt.get_entry(keys[0]);
+        tt.get_entry(keys[BUCKET_SIZE - 1]);
+        tt.record_cutoff();
+        assert_eq!(
+            tt.get_stats(),
+            TranspositionTableStats {
+                hits: 1,
+                misses: 1,
+                collisions: 1,
+                cutoffs: 1,
+            }
+        );
+
+        tt.clear();
+        assert_eq!(tt.get_stats(), TranspositionTableStats::default());
+    }
+
+    #[test]
+    fn test_tt_hashfull() {
+        let tt = build_new_tt(1);
+        assert_eq!(tt.hashfull(), 0);
+
+        // Fill a tenth of the sampled entries
+        let num_buckets = HASHFULL_SAMPLE_SIZE / BUCKET_SIZE / 10;
+        for key in 0..num_buckets as u64 {
+            for depth in 0..BUCKET_SIZE as u64 {
+                let key = key + depth * tt.num_buckets() as u64;
+                tt.set_entry(key, build_entry(key, 5));
+            }
+        }
+        assert_eq!(tt.hashfull(), 100);
+
+        // Entries of earlier searches are not counted
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    // Path of a file that is unique to the test
+    fn temp_file_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("requin_{}_{}.tt", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_tt_save_and_load() {
+        let tt = build_new_tt(1);
+        let keys = keys_of_same_bucket(&tt, BUCKET_SIZE);
+        for (depth, &key) in keys.iter().enumerate() {
+            tt.set_entry(key, build_entry(key, depth as u8));
+        }
+        let path = temp_file_path("save_and_load");
+        tt.save(&path).unwrap();
+
+        // Entries can be loaded into tables of another size
+        let loaded_tt = build_new_tt(2);
+        loaded_tt.load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        for (depth, &key) in keys.iter().enumerate() {
+            let entry = loaded_tt.get_entry(key);
+            assert!(entry.is_valid(key));
+            assert_eq!(entry.get_search_data().depth(), depth as u8);
+        }
+    }
+
+    #[test]
+    fn test_tt_load_rejects_incompatible_files() {
+        let path = temp_file_path("incompatible");
+        let tt = build_new_tt(1);
+        tt.save(&path).unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+
+        // Different Zobrist seed
+        bytes[12..20].copy_from_slice(&(ZOBRIST_SEED + 1).to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(
+            tt.load(&path),
+            Err("The file was saved with different Zobrist keys.")
+        );
+
+        // Different version
+        bytes[8..12].copy_from_slice(&(TT_FILE_VERSION + 1).to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(
+            tt.load(&path),
+            Err("The version of the file is not supported.")
+        );
+
+        // Entries that the engine could not have stored
+        let tt = build_new_tt(1);
+        for key in [1, 2] {
+            tt.set_entry(key, build_entry(key, 5));
+        }
+        tt.save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        // The depth and the node type are bits 32-39 and 40-41 of the search data,
+        // which follows the key and the move data of the second entry
+        let search_data_offset = 28 + 24 + 16;
+        for (offset, value) in [(4, MAX_SEARCH_DEPTH + 1), (5, 3)] {
+            let mut bytes = bytes.clone();
+            bytes[search_data_offset + offset] = value;
+            std::fs::write(&path, &bytes).unwrap();
+            let loaded_tt = build_new_tt(1);
+            assert_eq!(
+                loaded_tt.load(&path),
+                Err("The file contains an invalid entry.")
+            );
+            // Neither entry is stored
+            assert!(!loaded_tt.get_entry(1).is_valid(1));
+            assert!(!loaded_tt.get_entry(2).is_valid(2));
+        }
+
+        // Not a transposition table
+        std::fs::write(&path, b"position startpos").unwrap();
+        assert_eq!(
+            tt.load(&path),
+            Err("The file does not contain a transposition table.")
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tt_concurrent_access_never_returns_torn_entries() {
+        let tt = build_new_tt(1);
+        let keys = keys_of_same_bucket(&tt, 2 * BUCKET_SIZE);
+
+        std::thread::scope(|scope| {
+            for thread_idx in 0..4 {
+                let tt = tt.clone();
+                let keys = &keys;
+                scope.spawn(move || {
+                    for i in 0..10_000 {
+                        let key = keys[(i + thread_idx) % keys.len()];
+                        // The depth is derived from the key, so any mix
+                        // of two different entries can be recognised
+                        tt.set_entry(key, build_entry(key, key as u8));
+                        let entry = tt.get_entry(key);
+                        if entry.is_valid(key) {
+                            assert_eq!(entry.get_search_data().depth(), key as u8);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
     #[test]
     fn test_move_data_with_promotion() {
         let move_data = TranspositionTableEntryMoveData::new(
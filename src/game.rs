@@ -34,11 +34,13 @@ pub struct Game {
 
 impl Game {
     pub fn new(starting_board: Board) -> Game {
-        Game {
+        let mut game = Game {
             board_history: vec![(starting_board, 0)],
-            current_legal_moves: Some(generate_legal_moves(&starting_board)),
+            current_legal_moves: None,
             state: GameState::InProgress,
-        }
+        };
+        game.generate_legal_moves();
+        game
     }
 
     pub fn get_board(&self, idx: usize) -> Option<&Board> {
@@ -274,8 +276,33 @@ impl Game {
         }
     }
 
+    // Whether the game is over is only known once the legal moves are generated
     fn generate_legal_moves(&mut self) {
-        self.current_legal_moves = Some(generate_legal_moves(self.current_board()));
+        let legal_moves = generate_legal_moves(self.current_board());
+
+        if legal_moves.is_empty() {
+            if self.current_board().is_in_check() {
+                match self.current_board().get_player_color() {
+                    Color::White => {
+                        self.state = GameState::BlackWon;
+                    }
+                    Color::Black => {
+                        self.state = GameState::WhiteWon;
+                    }
+                }
+            } else {
+                self.state = GameState::Stalemate;
+            }
+        }
+        self.current_legal_moves = Some(legal_moves);
+    }
+
+    // Making or unmaking a move during the search discards the legal moves of
+    // the current position, this generates them again
+    pub(crate) fn restore_legal_moves(&mut self) {
+        if self.current_legal_moves.is_none() {
+            self.generate_legal_moves();
+        }
     }
 
     pub fn current_legal_moves(&self) -> &Vec<Move> {
@@ -291,31 +318,30 @@ impl Game {
 
     // This must not be called if the side to move is in check
     pub fn apply_null_move(&mut self) {
+        self.make_null_move();
+        self.generate_legal_moves();
+    }
+
+    pub fn apply_move(&mut self, m: &Move) {
+        self.make_move(m);
+        self.generate_legal_moves();
+    }
+
+    // Used by the search instead of apply_null_move, the legal moves of the new
+    // position are not generated and the game is not checked for a mate or a
+    // stalemate. Positions made this way must be left with unmake_move.
+    pub(crate) fn make_null_move(&mut self) {
         let mut new_board = self.current_board().clone();
-        new_board.set_player_color(new_board.get_opposing_player_color());
+        new_board.apply_null_move();
 
         // Null moves are reversible
         self.board_history
             .push((new_board, self.get_plies_from_reversible_move() + 1));
-
-        self.generate_legal_moves();
-        if self.current_legal_moves().len() == 0 {
-            if self.current_board().is_in_check() {
-                match self.current_board().get_player_color() {
-                    Color::White => {
-                        self.state = GameState::BlackWon;
-                    }
-                    Color::Black => {
-                        self.state = GameState::WhiteWon;
-                    }
-                }
-            } else {
-                self.state = GameState::Stalemate;
-            }
-        }
+        self.current_legal_moves = None;
     }
 
-    pub fn apply_move(&mut self, m: &Move) {
+    // Used by the search instead of apply_move, see make_null_move
+    pub(crate) fn make_move(&mut self, m: &Move) {
         let mut new_board = self.current_board().clone();
         new_board.apply_move(&m);
 
@@ -330,23 +356,7 @@ impl Game {
 
         self.board_history
             .push((new_board, plies_from_last_irreversible_move));
-
-        self.generate_legal_moves();
-
-        if self.current_legal_moves().len() == 0 {
-            if self.current_board().is_in_check() {
-                match self.current_board().get_player_color() {
-                    Color::White => {
-                        self.state = GameState::BlackWon;
-                    }
-                    Color::Black => {
-                        self.state = GameState::WhiteWon;
-                    }
-                }
-            } else {
-                self.state = GameState::Stalemate;
-            }
-        }
+        self.current_legal_moves = None;
     }
 
     pub fn apply_move_with_src_dest(
@@ -363,9 +373,15 @@ impl Game {
     }
 
     pub fn undo_move(&mut self) {
+        self.unmake_move();
+        self.state = GameState::InProgress;
+    }
+
+    // Used by the search instead of undo_move, the legal moves of the previous
+    // position are only generated again by restore_legal_moves
+    pub(crate) fn unmake_move(&mut self) {
         self.board_history.pop();
         self.current_legal_moves = None;
-        self.state = GameState::InProgress;
     }
 
     pub fn is_game_over(&self) -> bool {
@@ -970,4 +986,55 @@ mod game_tests {
         game.apply_move(&black_king_advance);
         assert!(game.is_threefold_repetition());
     }
+
+    #[test]
+    fn game_over_is_detected_with_legal_moves() {
+        // Fool's mate
+        let mut game = Game::new(Board::new_starting_pos());
+        for (src, dest) in [
+            (Coordinate::F2, Coordinate::F3),
+            (Coordinate::E7, Coordinate::E5),
+            (Coordinate::G2, Coordinate::G4),
+            (Coordinate::D8, Coordinate::H4),
+        ] {
+            game.apply_move_with_src_dest(src, dest, None).unwrap();
+        }
+        assert!(game.current_legal_moves().is_empty());
+        assert_eq!(game.state, GameState::BlackWon);
+
+        game.undo_move();
+        assert_eq!(game.state, GameState::InProgress);
+
+        // The search does not check the positions it makes for a mate
+        let mate = game
+            .current_board()
+            .build_move_with_src_dest(Coordinate::D8, Coordinate::H4, None)
+            .unwrap();
+        game.make_move(&mate);
+        assert_eq!(game.state, GameState::InProgress);
+        game.unmake_move();
+        game.restore_legal_moves();
+        assert_eq!(game.current_legal_moves().len(), 30);
+    }
+
+    #[test]
+    fn null_move() {
+        let mut game = Game::new(Board::new_starting_pos());
+        game.apply_move_with_src_dest(Coordinate::E2, Coordinate::E4, None)
+            .unwrap();
+        let zobrist = game.get_current_zobrist();
+
+        game.apply_null_move();
+        assert_eq!(game.current_board().get_player_color(), Color::White);
+        assert_eq!(game.current_board().get_en_passant_square(), None);
+        assert_ne!(game.get_current_zobrist(), zobrist);
+        assert_eq!(game.current_legal_moves().len(), 30);
+
+        game.undo_move();
+        assert_eq!(game.get_current_zobrist(), zobrist);
+        assert_eq!(
+            game.current_board().get_en_passant_square(),
+            Some(Coordinate::E3)
+        );
+    }
 }
@@ -1,6 +1,6 @@
 use crate::bitboard::*;
 use crate::board::{Board, Color, Coordinate, PieceType};
-use crate::r#move::Move;
+use crate::r#move::{CastlingSide, Move};
 
 use std::convert::TryFrom;
 
@@ -383,15 +383,7 @@ pub fn generate_moves(board: &Board) -> Vec<Move> {
         if let Some(piece) = piece {
             if color == piece.color {
                 let coord = Coordinate::try_from(i).unwrap();
-                let piece_moves = match piece.piece_type {
-                    PieceType::Pawn => generate_pawn_moves(board, coord),
-                    PieceType::Knight => generate_knight_moves(board, coord),
-                    PieceType::Bishop => generate_bishop_style_moves(board, coord),
-                    PieceType::Rook => generate_rook_style_moves(board, coord),
-                    PieceType::King => generate_king_moves(board, coord, true),
-                    PieceType::Queen => generate_queen_moves(board, coord),
-                };
-                res.extend(piece_moves);
+                res.extend(generate_piece_moves(board, coord, piece.piece_type));
             }
         }
     }
@@ -399,6 +391,18 @@ pub fn generate_moves(board: &Board) -> Vec<Move> {
     res
 }
 
+// Generate all moves for the piece on a particular square
+fn generate_piece_moves(board: &Board, src: Coordinate, piece_type: PieceType) -> Vec<Move> {
+    match piece_type {
+        PieceType::Pawn => generate_pawn_moves(board, src),
+        PieceType::Knight => generate_knight_moves(board, src),
+        PieceType::Bishop => generate_bishop_style_moves(board, src),
+        PieceType::Rook => generate_rook_style_moves(board, src),
+        PieceType::King => generate_king_moves(board, src, true),
+        PieceType::Queen => generate_queen_moves(board, src),
+    }
+}
+
 pub fn generate_legal_moves(board: &Board) -> Vec<Move> {
     // Filter out illegal moves, i.e. moves that endanger the king
     let player_color = board.get_player_color();
@@ -409,6 +413,85 @@ pub fn generate_legal_moves(board: &Board) -> Vec<Move> {
         .collect()
 }
 
+// Generate the legal captures and promotions, these are searched before the other
+// moves, so they are only generated together with the rest if they are needed
+pub fn generate_legal_captures(board: &Board) -> Vec<Move> {
+    generate_legal_moves_of_kind(board, true)
+}
+
+// Generate the legal moves that are neither captures nor promotions
+pub fn generate_legal_quiet_moves(board: &Board) -> Vec<Move> {
+    generate_legal_moves_of_kind(board, false)
+}
+
+fn generate_legal_moves_of_kind(board: &Board, captures: bool) -> Vec<Move> {
+    let mut res = vec![];
+    let player_color = board.get_player_color();
+
+    for (i, piece) in board.get_pieces().iter().enumerate() {
+        if let Some(piece) = piece {
+            if player_color == piece.color {
+                let coord = Coordinate::try_from(i).unwrap();
+                // Castling is never a capture
+                let moves = match piece.piece_type {
+                    PieceType::King => generate_king_moves(board, coord, !captures),
+                    _ => generate_piece_moves(board, coord, piece.piece_type),
+                };
+                res.extend(moves.into_iter().filter(|m| {
+                    (m.is_capture || m.is_promotion) == captures
+                        && is_move_legal(board, player_color, m)
+                }));
+            }
+        }
+    }
+
+    res
+}
+
+// Whether the side to move has a legal move, this stops at the first one found
+pub fn has_legal_move(board: &Board) -> bool {
+    let player_color = board.get_player_color();
+
+    board
+        .get_pieces()
+        .iter()
+        .enumerate()
+        .any(|(i, piece)| match piece {
+            Some(piece) if piece.color == player_color => {
+                let coord = Coordinate::try_from(i).unwrap();
+                // Castling can be skipped, if it is legal then so is the
+                // king's step towards the rook
+                let moves = match piece.piece_type {
+                    PieceType::King => generate_king_moves(board, coord, false),
+                    _ => generate_piece_moves(board, coord, piece.piece_type),
+                };
+                moves.iter().any(|m| is_move_legal(board, player_color, m))
+            }
+            _ => false,
+        })
+}
+
+// Find the legal move from the source to the destination square (promoting to the
+// given piece type), this only generates the moves of the piece on the source square
+// so that moves from elsewhere (e.g. the TT) can be verified cheaply
+pub fn find_legal_move(
+    board: &Board,
+    src: Coordinate,
+    dest: Coordinate,
+    promotes_to: Option<PieceType>,
+) -> Option<Move> {
+    let piece = board.get_from_coordinate(src)?;
+    let player_color = board.get_player_color();
+    if piece.color != player_color {
+        return None;
+    }
+
+    generate_piece_moves(board, src, piece.piece_type)
+        .into_iter()
+        .find(|m| m.dest == dest && m.promotes_to == promotes_to)
+        .filter(|m| is_move_legal(board, player_color, m))
+}
+
 pub fn generate_non_quiescent_moves(board: &Board) -> Vec<Move> {
     // Filter out illegal moves, i.e. moves that endanger the king.
     // Also filter out moves that aren't captures unless we are in check
@@ -433,6 +516,59 @@ pub fn filter_non_quiescent_moves(board: &Board, moves: Vec<Move>) -> Vec<Move>
         .collect()
 }
 
+// Determines whether a legal move gives check without applying it, the check is either
+// given by the moving piece or discovered by a slider that the moving piece was blocking
+pub fn gives_check(board: &Board, m: &Move) -> bool {
+    let color = m.piece.color;
+    let king_bb = match board.get_king_coordinate(color.other_color()) {
+        Some(king_coord) => king_coord.to_bb(),
+        None => return false,
+    };
+
+    if m.is_castling() {
+        // Only the rook can give check after castling
+        let rank = m.src.get_rank();
+        let (rook_src, rook_dest) = match m.castling_side {
+            CastlingSide::Kingside => (
+                Coordinate::new_from_rank_file(rank, 8),
+                Coordinate::new_from_rank_file(rank, 6),
+            ),
+            _ => (
+                Coordinate::new_from_rank_file(rank, 1),
+                Coordinate::new_from_rank_file(rank, 4),
+            ),
+        };
+        let occupied = (board.get_all_pieces_bb() ^ m.src.to_bb() ^ rook_src.to_bb())
+            | m.dest.to_bb()
+            | rook_dest.to_bb();
+        return get_sliding_attacks_occupied(PieceType::Rook, rook_dest, occupied) & king_bb != 0;
+    }
+
+    let mut occupied = (board.get_all_pieces_bb() ^ m.src.to_bb()) | m.dest.to_bb();
+    if m.is_en_passant {
+        // The captured pawn is on the square behind the destination square
+        occupied ^= m.dest.vertical_offset(1, !color.is_white()).to_bb();
+    }
+
+    let piece_type = m.promotes_to.unwrap_or(m.piece.piece_type);
+    let attacks = match piece_type {
+        PieceType::Pawn => get_pawn_attacks_bb(color, m.dest),
+        PieceType::Knight | PieceType::King => get_piece_attacks_bb(piece_type, m.dest),
+        _ => get_sliding_attacks_occupied(piece_type, m.dest, occupied),
+    };
+    if attacks & king_bb != 0 {
+        return true;
+    }
+
+    let king_coord = Coordinate::from_bb(king_bb);
+    let discovered_attackers =
+        (get_sliding_attacks_occupied(PieceType::Rook, king_coord, occupied)
+            & board.get_piece_types_bb_for_color(PieceType::Rook, PieceType::Queen, color))
+            | (get_sliding_attacks_occupied(PieceType::Bishop, king_coord, occupied)
+                & board.get_piece_types_bb_for_color(PieceType::Bishop, PieceType::Queen, color));
+    discovered_attackers & !m.src.to_bb() != 0
+}
+
 // Get bitboard that represents all attackers (of a particular color) of a particular square.
 // This includes direct attacks only, i.e. this takes into account obstacles on the board.
 pub fn get_attackers_of_square_bb(
@@ -514,6 +650,7 @@ pub fn are_squares_controlled_by_player(
 mod test {
     use super::*;
     use crate::board::{Color, Piece};
+    use crate::parser::parse_fen;
 
     #[test]
     fn generate_basic_pawn_moves() {
@@ -2422,4 +2559,88 @@ mod test {
                 | Coordinate::F6.to_bb()
         );
     }
+
+    #[test]
+    fn find_legal_move_checks_piece_moves() {
+        // White's d2 pawn is pinned by the bishop on b4
+        let board = parse_fen("4k3/8/8/8/1b6/8/3P4/4K1N1 w - - 0 1".into()).unwrap();
+
+        let m = find_legal_move(&board, Coordinate::G1, Coordinate::F3, None).unwrap();
+        assert_eq!(m.piece, Piece::new(Color::White, PieceType::Knight));
+        assert!(!m.is_capture);
+        // Not a knight move
+        assert!(find_legal_move(&board, Coordinate::G1, Coordinate::G3, None).is_none());
+        // Moves a pinned piece
+        assert!(find_legal_move(&board, Coordinate::D2, Coordinate::D3, None).is_none());
+        // Moves an opposing piece
+        assert!(find_legal_move(&board, Coordinate::B4, Coordinate::C3, None).is_none());
+        // Moves from an empty square
+        assert!(find_legal_move(&board, Coordinate::A1, Coordinate::A2, None).is_none());
+    }
+
+    #[test]
+    fn legal_captures_and_quiet_moves_make_up_legal_moves() {
+        let fens = [
+            "r3k2r/pppq1ppp/2n2n2/1Bb1p3/4P3/2NP1N2/PPP2PPP/R2QK2R b KQkq - 0 1",
+            // Promotions with and without captures, and en passant
+            "1n2k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            // Evasions of a check
+            "4k3/8/8/8/7b/8/3P4/R3K2R w KQ - 0 1",
+        ];
+        for fen in fens {
+            let board = parse_fen(fen.into()).unwrap();
+            let captures = generate_legal_captures(&board);
+            let quiet_moves = generate_legal_quiet_moves(&board);
+            assert!(captures.iter().all(|m| m.is_capture || m.is_promotion));
+            assert!(quiet_moves.iter().all(|m| !m.is_capture && !m.is_promotion));
+
+            let legal_moves = generate_legal_moves(&board);
+            assert_eq!(captures.len() + quiet_moves.len(), legal_moves.len());
+            assert!(legal_moves
+                .iter()
+                .all(|m| captures.contains(m) || quiet_moves.contains(m)));
+        }
+    }
+
+    #[test]
+    fn has_legal_move_detects_stalemate() {
+        let fens = [
+            ("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1", false),
+            ("k7/8/1K6/8/8/8/8/2Q5 b - - 0 1", true),
+            // Only a pawn can move
+            ("k7/2Q5/1K6/8/8/8/p7/8 b - - 0 1", true),
+        ];
+        for (fen, expected) in fens {
+            let board = parse_fen(fen.into()).unwrap();
+            assert_eq!(has_legal_move(&board), expected);
+            assert_eq!(!generate_legal_moves(&board).is_empty(), expected);
+        }
+    }
+
+    #[test]
+    fn gives_check_matches_applied_moves() {
+        let fens = [
+            // Direct and discovered checks by every piece type
+            "4k3/8/8/4N3/3B4/8/4R3/R3K2R w KQ - 0 1",
+            // Castling with check and promotions with check
+            "5k2/1P6/8/8/8/8/8/4K2R w K - 0 1",
+            // En passant that discovers a check along the rank
+            "8/8/8/k2pP2Q/8/8/8/7K w - d6 0 1",
+            "r3k2r/pppq1ppp/2n2n2/1Bb1p3/4P3/2NP1N2/PPP2PPP/R2QK2R b KQkq - 0 1",
+        ];
+        for fen in fens {
+            let board = parse_fen(fen.into()).unwrap();
+            for m in generate_legal_moves(&board) {
+                let mut new_board = board.clone();
+                new_board.apply_move(&m);
+                assert_eq!(
+                    gives_check(&board, &m),
+                    new_board.is_in_check(),
+                    "{:?} in {}",
+                    m,
+                    fen
+                );
+            }
+        }
+    }
 }
@@ -1,4 +1,3 @@
-#![feature(deadline_api)]
 #![feature(div_duration)]
 
 pub mod bitboard;
@@ -13,7 +12,7 @@ pub mod zobrist;
 
 pub use bitboard::init_tables;
 use board::Board;
-use engine::Searcher;
+use engine::{SearchResult, SearchScore, Searcher};
 use game::Game;
 use uci::Client;
 
@@ -42,18 +41,24 @@ pub fn play_game_ai(ai_starts: bool, depth: u8, num_threads: usize) {
     // 0 implies that it is the AI's turn
     let turn_seq = if ai_starts { [0, 1] } else { [1, 0] };
     let mut turn_seq_iterator = turn_seq.iter().cycle();
+    // Result of the engine's last search, shown below the board
+    let mut last_result: Option<SearchResult> = None;
 
     loop {
         clear_screen();
         searcher.game.print_current_board();
 
+        if let Some(result) = &last_result {
+            println!("{}", format_engine_line(result));
+        }
+
         if searcher.game.is_game_over() {
             println!("Game over. Result: {}", searcher.game.state.to_text());
             exit(exitcode::OK);
         }
 
         if *turn_seq_iterator.next().unwrap() == 0 {
-            searcher.apply_best_move();
+            last_result = Some(searcher.apply_best_move());
         } else {
             // Get next move from user
             searcher.game.get_next_move();
@@ -61,6 +66,20 @@ pub fn play_game_ai(ai_starts: bool, depth: u8, num_threads: usize) {
     }
 }
 
+fn format_engine_line(result: &SearchResult) -> String {
+    let score = match result.score {
+        SearchScore::Centipawns(cp) => format!("{:+.2}", cp as f32 / 100.0),
+        SearchScore::Mate(moves) => format!("#{}", moves),
+    };
+    let pv = result
+        .pv
+        .iter()
+        .map(|m| m.to_algebraic_notation())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("Engine evaluation: {} Line: {}", score, pv)
+}
+
 pub fn play_game_pvp() {
     let board = Board::new_starting_pos();
     let mut game = Game::new(board);
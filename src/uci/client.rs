@@ -55,10 +55,14 @@ impl Client {
                 r"^position fen (([prnbqkPRNBQK12345678]{1,8}(?:/[prnbqkPRNBQK12345678]{1,8}){7})\s+(w|b)\s+([KQkq]{1,4}|-)\s+(-|[a-h][1-8])\s(\d+\s\d+))(\s+moves ([a-h][1-8][a-h][1-8][nbrq]?(\s[a-h][1-8][a-h][1-8][nbrq]?)*))?"
             ).unwrap();
             static ref POSITION_WITH_STARTPOS: Regex = Regex::new(r"position startpos(\s+moves ([a-h][1-8][a-h][1-8][nbrq]?(\s[a-h][1-8][a-h][1-8][nbrq]?)*))?").unwrap();
-            static ref GO: Regex = Regex::new(r"^go((\s+(ponder|infinite|searchmoves(\s+[a-h][1-8][a-h][1-8])+|(wtime|btime|winc|binc|depth|movestogo|nodes|mate|movetime)\s+(\d+)))*)?").unwrap();
+            static ref GO: Regex = Regex::new(r"^go((\s+(ponder|infinite|searchmoves(\s+[a-h][1-8][a-h][1-8][qrbn]?)+|(wtime|btime|winc|binc|depth|movestogo|nodes|mate|movetime)\s+(\d+)))*)?").unwrap();
             static ref STOP: Regex = Regex::new(r"^stop").unwrap();
             static ref PONDERHIT: Regex = Regex::new(r"^ponderhit").unwrap();
-            static ref SETOPTION: Regex = Regex::new(r"^setoption name (\w+) value (\w+)").unwrap();
+            // Option names may contain spaces, and buttons do not have a value
+            static ref SETOPTION: Regex = Regex::new(r"^setoption name (.+?)(?:\s+value\s+(.+))?$").unwrap();
+            // Extensions of the protocol to persist the transposition table
+            static ref SAVEHASH: Regex = Regex::new(r"^savehash\s+(.+)$").unwrap();
+            static ref LOADHASH: Regex = Regex::new(r"^loadhash\s+(.+)$").unwrap();
             static ref QUIT: Regex = Regex::new(r"^quit").unwrap();
         }
 
@@ -115,13 +119,25 @@ impl Client {
                 .handle_ponderhit(Arc::clone(&self.state), Output::new(std::io::stdout()));
         } else if let Some(m) = SETOPTION.captures(&cmd) {
             let arg_name = m[1].to_string();
-            let arg_val = m[2].to_string();
+            let arg_val = m.get(2).map_or("", |v| v.as_str()).to_string();
             self.handler.handle_setoption(
                 Arc::clone(&self.state),
                 Output::new(std::io::stdout()),
                 arg_name,
                 arg_val,
             );
+        } else if let Some(m) = SAVEHASH.captures(&cmd) {
+            self.handler.handle_savehash(
+                Arc::clone(&self.state),
+                Output::new(std::io::stdout()),
+                m[1].to_string(),
+            );
+        } else if let Some(m) = LOADHASH.captures(&cmd) {
+            self.handler.handle_loadhash(
+                Arc::clone(&self.state),
+                Output::new(std::io::stdout()),
+                m[1].to_string(),
+            );
         } else if let Some(_) = QUIT.captures(&cmd) {
             std::process::exit(exitcode::OK);
         } else {
@@ -391,6 +407,23 @@ mod test {
         client.handle_command("go depth 5 movestogo 5 searchmoves e2e4 d2d4 nodes 1");
     }
 
+    #[test]
+    fn test_handle_go_with_promotion_search_move() {
+        let mut mock_handler = UCIHandler::default();
+        mock_handler
+            .expect_handle_go::<Output<std::io::Stdout>>()
+            .with(
+                predicate::always(),
+                predicate::always(),
+                predicate::eq(String::from(" searchmoves b7b8q b7b8n depth 4")),
+            )
+            .times(1)
+            .returning(|_, _, _| ());
+
+        let mut client = Client::new_with_handler(mock_handler);
+        client.handle_command("go searchmoves b7b8q b7b8n depth 4");
+    }
+
     #[test]
     fn test_handle_stop() {
         let mut mock_handler = UCIHandler::default();
@@ -434,4 +467,56 @@ mod test {
         let mut client = Client::new_with_handler(mock_handler);
         client.handle_command("setoption name NumThreads value 32");
     }
+
+    #[test]
+    fn test_handle_setoption_with_spaces_in_name() {
+        let mut mock_handler = UCIHandler::default();
+        mock_handler
+            .expect_handle_setoption::<Output<std::io::Stdout>>()
+            .with(
+                predicate::always(),
+                predicate::always(),
+                predicate::eq(String::from("Clear Hash")),
+                predicate::eq(String::from("")),
+            )
+            .times(1)
+            .returning(|_, _, _, _| ());
+
+        let mut client = Client::new_with_handler(mock_handler);
+        client.handle_command("setoption name Clear Hash");
+    }
+
+    #[test]
+    fn test_handle_savehash() {
+        let mut mock_handler = UCIHandler::default();
+        mock_handler
+            .expect_handle_savehash::<Output<std::io::Stdout>>()
+            .with(
+                predicate::always(),
+                predicate::always(),
+                predicate::eq(String::from("/tmp/analysis.tt")),
+            )
+            .times(1)
+            .returning(|_, _, _| ());
+
+        let mut client = Client::new_with_handler(mock_handler);
+        client.handle_command("savehash /tmp/analysis.tt");
+    }
+
+    #[test]
+    fn test_handle_loadhash() {
+        let mut mock_handler = UCIHandler::default();
+        mock_handler
+            .expect_handle_loadhash::<Output<std::io::Stdout>>()
+            .with(
+                predicate::always(),
+                predicate::always(),
+                predicate::eq(String::from("/tmp/analysis.tt")),
+            )
+            .times(1)
+            .returning(|_, _, _| ());
+
+        let mut client = Client::new_with_handler(mock_handler);
+        client.handle_command("loadhash /tmp/analysis.tt");
+    }
 }
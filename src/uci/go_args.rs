@@ -1,4 +1,11 @@
-use crate::board::{Coordinate, PieceType};
+use super::time::max_search_time;
+use crate::board::{Color, Coordinate, PieceType};
+use crate::engine::SearchLimits;
+
+use std::str::FromStr;
+
+// Used when the GUI does not limit the search in any way
+const DEFAULT_SEARCH_DEPTH: u32 = 5;
 
 #[allow(dead_code)]
 pub struct GoArgs {
@@ -9,10 +16,10 @@ pub struct GoArgs {
     pub winc: Option<u32>,
     pub binc: Option<u32>,
     pub movestogo: Option<u32>,
-    pub depth: u8,
-    pub nodes: u32,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
     pub mate: Option<u32>,
-    pub movetime: u32,
+    pub movetime: Option<u32>,
     pub infinite: bool,
 }
 
@@ -26,51 +33,48 @@ impl GoArgs {
             winc: None,
             binc: None,
             movestogo: None,
-            depth: 5,
-            nodes: 10,
+            depth: None,
+            nodes: None,
             mate: None,
-            movetime: 1000,
-            infinite: true,
+            movetime: None,
+            infinite: false,
         }
     }
 
-    // This function assumes that the args_str is valid,
-    // i.e. it conforms to the regex of the Go command
-    pub fn new_from_args_str(args_str: String) -> Self {
+    // This function assumes that the args_str conforms to the regex of the
+    // Go command, values that do not fit into their type are rejected
+    pub fn new_from_args_str(args_str: String) -> Result<Self, &'static str> {
         let mut arg_vec = args_str.split_whitespace().peekable();
         let mut res = GoArgs::new();
 
         while let Some(arg_key) = arg_vec.next() {
             match arg_key {
                 "wtime" => {
-                    // Assume that format of args are correct, i.e.
-                    // the below should never fail
-
-                    res.wtime = Some(arg_vec.next().unwrap().parse::<u32>().unwrap());
+                    res.wtime = Some(parse_arg_value(arg_vec.next())?);
                 }
                 "btime" => {
-                    res.btime = Some(arg_vec.next().unwrap().parse::<u32>().unwrap());
+                    res.btime = Some(parse_arg_value(arg_vec.next())?);
                 }
                 "winc" => {
-                    res.winc = Some(arg_vec.next().unwrap().parse::<u32>().unwrap());
+                    res.winc = Some(parse_arg_value(arg_vec.next())?);
                 }
                 "binc" => {
-                    res.binc = Some(arg_vec.next().unwrap().parse::<u32>().unwrap());
+                    res.binc = Some(parse_arg_value(arg_vec.next())?);
                 }
                 "movestogo" => {
-                    res.movestogo = Some(arg_vec.next().unwrap().parse::<u32>().unwrap());
+                    res.movestogo = Some(parse_arg_value(arg_vec.next())?);
                 }
                 "depth" => {
-                    res.depth = arg_vec.next().unwrap().parse::<u8>().unwrap();
+                    res.depth = Some(parse_arg_value(arg_vec.next())?);
                 }
                 "nodes" => {
-                    res.nodes = arg_vec.next().unwrap().parse::<u32>().unwrap();
+                    res.nodes = Some(parse_arg_value(arg_vec.next())?);
                 }
                 "mate" => {
-                    res.mate = Some(arg_vec.next().unwrap().parse::<u32>().unwrap());
+                    res.mate = Some(parse_arg_value(arg_vec.next())?);
                 }
                 "movetime" => {
-                    res.movetime = arg_vec.next().unwrap().parse::<u32>().unwrap();
+                    res.movetime = Some(parse_arg_value(arg_vec.next())?);
                 }
                 "infinite" => {
                     res.infinite = true;
@@ -108,32 +112,83 @@ impl GoArgs {
             }
         }
 
-        res
+        Ok(res)
+    }
+
+    pub fn get_search_limits(&self, player_color: Color) -> SearchLimits {
+        let (player_time, player_increment) = match player_color {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        let time_limit = player_time.map(|t| max_search_time(t, player_increment.unwrap_or(0)));
+
+        // Only fall back to the default depth when nothing else limits the search
+        let is_unlimited = time_limit.is_none()
+            && self.nodes.is_none()
+            && self.movetime.is_none()
+            && self.mate.is_none()
+            && !self.infinite;
+        let depth = if is_unlimited {
+            self.depth.or(Some(DEFAULT_SEARCH_DEPTH))
+        } else {
+            self.depth
+        };
+
+        SearchLimits {
+            depth,
+            nodes: self.nodes,
+            movetime: self.movetime,
+            time_limit,
+            infinite: self.infinite,
+            mate: self.mate,
+            search_moves: self.search_moves.clone(),
+        }
     }
 }
 
+fn parse_arg_value<T: FromStr>(arg_value: Option<&str>) -> Result<T, &'static str> {
+    arg_value
+        .and_then(|v| v.parse::<T>().ok())
+        .ok_or("Invalid value in the arguments of the go command.")
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
     fn test_args_parser_with_one_kv_pair() {
-        let args = GoArgs::new_from_args_str(" depth 47 ".into());
-        assert_eq!(args.depth, 47);
+        let args = GoArgs::new_from_args_str(" depth 47 ".into()).unwrap();
+        assert_eq!(args.depth, Some(47));
     }
 
     #[test]
     fn test_args_parser_with_multiple_kv_pairs() {
-        let args = GoArgs::new_from_args_str(" depth 47 winc 3000 binc 1000 nodes 24 ".into());
-        assert_eq!(args.depth, 47);
-        assert_eq!(args.nodes, 24);
+        let args =
+            GoArgs::new_from_args_str(" depth 47 winc 3000 binc 1000 nodes 24 ".into()).unwrap();
+        assert_eq!(args.depth, Some(47));
+        assert_eq!(args.nodes, Some(24));
         assert_eq!(args.winc, Some(3000));
         assert_eq!(args.binc, Some(1000));
     }
 
+    #[test]
+    fn test_args_parser_with_deep_depth() {
+        // Deeper than the engine can search, the search clamps it
+        let args = GoArgs::new_from_args_str(" depth 300 ".into()).unwrap();
+        assert_eq!(args.depth, Some(300));
+        assert_eq!(args.get_search_limits(Color::White).depth, Some(300));
+    }
+
+    #[test]
+    fn test_args_parser_with_invalid_value() {
+        assert!(GoArgs::new_from_args_str(" wtime 4294967296 ".into()).is_err());
+        assert!(GoArgs::new_from_args_str(" depth 5 nodes 99999999999999999999".into()).is_err());
+    }
+
     #[test]
     fn test_args_parser_with_searchmoves() {
-        let args = GoArgs::new_from_args_str(" searchmoves e2e4 d2d4 ".into());
+        let args = GoArgs::new_from_args_str(" searchmoves e2e4 d2d4 ".into()).unwrap();
         assert_eq!(
             args.search_moves,
             Some(vec![
@@ -145,7 +200,8 @@ mod test {
 
     #[test]
     fn test_args_parser_with_searchmoves_and_other_args() {
-        let args = GoArgs::new_from_args_str(" depth 250 searchmoves e2e4 d2d4 nodes 14".into());
+        let args =
+            GoArgs::new_from_args_str(" depth 250 searchmoves e2e4 d2d4 nodes 14".into()).unwrap();
         assert_eq!(
             args.search_moves,
             Some(vec![
@@ -153,7 +209,71 @@ mod test {
                 (Coordinate::D2, Coordinate::D4, None)
             ])
         );
-        assert_eq!(args.depth, 250);
-        assert_eq!(args.nodes, 14);
+        assert_eq!(args.depth, Some(250));
+        assert_eq!(args.nodes, Some(14));
+    }
+
+    #[test]
+    fn test_search_limits_without_args() {
+        let limits = GoArgs::new_from_args_str("".into())
+            .unwrap()
+            .get_search_limits(Color::White);
+        assert_eq!(limits, SearchLimits::new_with_depth(DEFAULT_SEARCH_DEPTH));
+    }
+
+    #[test]
+    fn test_search_limits_with_nodes_and_movetime() {
+        let limits = GoArgs::new_from_args_str(" nodes 20000 movetime 300".into())
+            .unwrap()
+            .get_search_limits(Color::Black);
+        assert_eq!(limits.depth, None);
+        assert_eq!(limits.nodes, Some(20000));
+        assert_eq!(limits.movetime, Some(300));
+        assert_eq!(limits.time_limit, None);
+        assert!(!limits.infinite);
+    }
+
+    #[test]
+    fn test_search_limits_with_clock_of_player() {
+        let args = GoArgs::new_from_args_str(" wtime 30000 btime 60000 depth 8".into()).unwrap();
+        let white_limits = args.get_search_limits(Color::White);
+        let black_limits = args.get_search_limits(Color::Black);
+        assert_eq!(white_limits.time_limit, Some(max_search_time(30000, 0)));
+        assert_eq!(black_limits.time_limit, Some(max_search_time(60000, 0)));
+        assert_eq!(white_limits.depth, Some(8));
+    }
+
+    #[test]
+    fn test_search_limits_with_searchmoves() {
+        let limits = GoArgs::new_from_args_str(" searchmoves e2e4 d2d4 depth 3".into())
+            .unwrap()
+            .get_search_limits(Color::White);
+        assert_eq!(
+            limits.search_moves,
+            Some(vec![
+                (Coordinate::E2, Coordinate::E4, None),
+                (Coordinate::D2, Coordinate::D4, None)
+            ])
+        );
+        assert_eq!(limits.depth, Some(3));
+    }
+
+    #[test]
+    fn test_search_limits_with_mate() {
+        let limits = GoArgs::new_from_args_str(" mate 3".into())
+            .unwrap()
+            .get_search_limits(Color::White);
+        assert_eq!(limits.mate, Some(3));
+        // The search ends once the mate is found, so it does not need a depth
+        assert_eq!(limits.depth, None);
+    }
+
+    #[test]
+    fn test_search_limits_infinite() {
+        let limits = GoArgs::new_from_args_str(" infinite".into())
+            .unwrap()
+            .get_search_limits(Color::White);
+        assert!(limits.infinite);
+        assert_eq!(limits.depth, None);
     }
 }
@@ -1,23 +1,58 @@
-use super::time::max_search_time;
 use super::{ArcMutexUCIState, GoArgs, UCIOption, UCIOptionType};
-use crate::board::{Board, Color, Coordinate};
-use crate::engine::Searcher;
+use crate::board::{Board, Coordinate};
+use crate::engine::{
+    build_new_tt, SearchParams, SearchReport, SearchResult, SearchScore, Searcher,
+    DEFAULT_TT_SIZE_MB,
+};
 use crate::game::Game;
 use crate::parser::parse_fen;
 
 use lazy_static::lazy_static;
 use mockall_double::double;
 use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
 
 lazy_static! {
-    static ref UCI_OPTIONS: [UCIOption; 1] = {
-        [UCIOption::new(
-            "NumThreads".into(),
-            UCIOptionType::Spin,
-            16,
-            1,
-            32,
-        )]
+    static ref UCI_OPTIONS: [UCIOption; 11] = {
+        [
+            UCIOption::new("NumThreads".into(), UCIOptionType::Spin, 16, 1, 32),
+            UCIOption::new(
+                "Hash".into(),
+                UCIOptionType::Spin,
+                DEFAULT_TT_SIZE_MB as u32,
+                1,
+                65536,
+            ),
+            UCIOption::new("Clear Hash".into(), UCIOptionType::Button, 0, 0, 0),
+            UCIOption::new("Ponder".into(), UCIOptionType::Check, 0, 0, 1),
+            UCIOption::new("MultiPV".into(), UCIOptionType::Spin, 1, 1, 256),
+            UCIOption::new("RecaptureExtension".into(), UCIOptionType::Check, 1, 0, 1),
+            UCIOption::new("PassedPawnExtension".into(), UCIOptionType::Check, 1, 0, 1),
+            UCIOption::new(
+                "ReverseFutilityMargin".into(),
+                UCIOptionType::Spin,
+                SearchParams::default().reverse_futility_margin as u32,
+                0,
+                5000,
+            ),
+            UCIOption::new(
+                "RazoringMargin".into(),
+                UCIOptionType::Spin,
+                SearchParams::default().razoring_margin as u32,
+                0,
+                5000,
+            ),
+            UCIOption::new(
+                "ProbCutMargin".into(),
+                UCIOptionType::Spin,
+                SearchParams::default().probcut_margin as u32,
+                0,
+                5000,
+            ),
+            UCIOption::new("QuiescenceChecks".into(), UCIOptionType::Check, 1, 0, 1),
+        ]
     };
 }
 
@@ -58,9 +93,12 @@ mod mockable {
 
         pub fn handle_ucinewgame<W: Write + Send + 'static>(
             &mut self,
-            _state: ArcMutexUCIState,
-            _output: W,
+            state: ArcMutexUCIState,
+            output: W,
         ) {
+            // This cannot be done asynchronously since the next
+            // search must not see the results of the old game
+            ucinewgame(state, output);
         }
 
         pub fn handle_position_fen<W: Write + Send + 'static>(
@@ -89,11 +127,26 @@ mod mockable {
         pub fn handle_go<W: Write + Send + 'static>(
             &mut self,
             state: ArcMutexUCIState,
-            output: W,
+            mut output: W,
             args_str: String,
         ) {
+            let go_args = match GoArgs::new_from_args_str(args_str) {
+                Ok(go_args) => go_args,
+                Err(e) => {
+                    writeln!(output, "info string {}", e).unwrap();
+                    output.flush().unwrap();
+                    return;
+                }
+            };
+            // The signals have to be set before the search is spawned, otherwise
+            // a `stop` or `ponderhit` that immediately follows could get lost.
+            {
+                let state = state.lock().unwrap();
+                state.stop_signal.store(false, Ordering::SeqCst);
+                state.ponder_signal.store(go_args.ponder, Ordering::SeqCst);
+            }
             thread::spawn(move || {
-                go(state, output, args_str);
+                go(state, output, go_args);
             });
         }
 
@@ -126,6 +179,26 @@ mod mockable {
         ) {
             set_option(state, output, arg_name, args_val);
         }
+
+        pub fn handle_savehash<W: Write + Send + 'static>(
+            &mut self,
+            state: ArcMutexUCIState,
+            output: W,
+            path: String,
+        ) {
+            savehash(state, output, path);
+        }
+
+        pub fn handle_loadhash<W: Write + Send + 'static>(
+            &mut self,
+            state: ArcMutexUCIState,
+            output: W,
+            path: String,
+        ) {
+            // This cannot be done asynchronously since the
+            // next search should use the loaded entries
+            loadhash(state, output, path);
+        }
     }
 }
 
@@ -149,14 +222,18 @@ fn uci<W: Write + Send + 'static>(state: ArcMutexUCIState, mut output: W) {
 
 fn format_uci_option(uci_option: &UCIOption) -> String {
     // TODO: Handle other types
-    let type_string = match uci_option.option_type {
-        UCIOptionType::Spin => "spin",
-        // _ => panic!("Unexpected UCIOptionType"),
-    };
-    format!(
-        "option name {} type {} default {} min {} max {}",
-        uci_option.name, type_string, uci_option.default, uci_option.min, uci_option.max
-    )
+    match uci_option.option_type {
+        UCIOptionType::Spin => format!(
+            "option name {} type spin default {} min {} max {}",
+            uci_option.name, uci_option.default, uci_option.min, uci_option.max
+        ),
+        UCIOptionType::Check => format!(
+            "option name {} type check default {}",
+            uci_option.name,
+            uci_option.default != 0
+        ),
+        UCIOptionType::Button => format!("option name {} type button", uci_option.name),
+    }
 }
 
 fn isready<W: Write + Send + 'static>(mut output: W) {
@@ -164,6 +241,10 @@ fn isready<W: Write + Send + 'static>(mut output: W) {
     output.flush().unwrap();
 }
 
+fn ucinewgame<W: Write + Send + 'static>(state: ArcMutexUCIState, _output: W) {
+    state.lock().unwrap().tt.clear();
+}
+
 fn position_with_fen<W: Write + Send + 'static>(
     state: ArcMutexUCIState,
     _output: W,
@@ -185,52 +266,153 @@ fn position_with_startpos<W: Write + Send + 'static>(
     apply_moves_and_set_state::<W>(state, Game::new(Board::new_starting_pos()), moves);
 }
 
-fn go<W: Write + Send + 'static>(state: ArcMutexUCIState, mut output: W, args_str: String) {
-    let mut state = state.lock().unwrap();
-    let go_args = GoArgs::new_from_args_str(args_str);
-    let depth = go_args.depth;
-
-    let game = match &state.game {
-        Some(g) => g.clone(),
-        None => {
-            let board = Board::new_starting_pos();
-            let game = Game::new(board);
-            state.game = Some(game.clone());
-            game
-        }
-    };
+fn go<W: Write + Send + 'static>(state: ArcMutexUCIState, output: W, go_args: GoArgs) {
+    // Shared with the reporter which prints info lines during the search
+    let output = Arc::new(Mutex::new(output));
 
-    let player_color = game.current_board().get_player_color();
-    let (player_time, player_increment) = match player_color {
-        Color::White => (go_args.wtime, go_args.winc),
-        Color::Black => (go_args.btime, go_args.binc),
-    };
+    // The state must not stay locked during the search, otherwise
+    // commands like `stop` would be blocked until the search ends.
+    let (mut searcher, limits) = {
+        let mut state = state.lock().unwrap();
+        let game = match &state.game {
+            Some(g) => g.clone(),
+            None => {
+                let board = Board::new_starting_pos();
+                let game = Game::new(board);
+                state.game = Some(game.clone());
+                game
+            }
+        };
 
-    state.go_args = Some(go_args);
+        let limits = go_args.get_search_limits(game.current_board().get_player_color());
+        state.go_args = Some(go_args);
 
-    let mut searcher = Searcher::new(game, depth, state.num_threads);
-    let search_time = player_time.map(|t| max_search_time(t, player_increment.unwrap_or(0)));
+        // The default search depth is not used since the limits define the depth
+        let mut searcher = Searcher::new_with_tt(game, 0, state.num_threads, state.tt.clone());
+        searcher.set_stop_signal(Arc::clone(&state.stop_signal));
+        searcher.set_ponder_signal(Arc::clone(&state.ponder_signal));
+        searcher.set_multi_pv(state.multi_pv);
+        searcher.set_search_params(state.search_params.clone());
+        let reporter_output = Arc::clone(&output);
+        searcher.set_reporter(Arc::new(move |report| {
+            let mut output = reporter_output.lock().unwrap();
+            writeln!(output, "{}", format_search_report(&report)).unwrap();
+            output.flush().unwrap();
+        }));
+        (searcher, limits)
+    };
 
-    match searcher.get_best_move(search_time) {
-        Ok(best_move) => {
-            writeln!(
-                output,
-                "bestmove {}",
-                best_move.to_long_algebraic_notation()
-            )
-            .unwrap();
+    match searcher.search(&limits) {
+        Ok(result) => {
+            let mut output = output.lock().unwrap();
+            if let Some(mate) = limits.mate {
+                if !matches!(result.score, SearchScore::Mate(moves) if moves > 0 && moves as u32 <= mate)
+                {
+                    writeln!(output, "info string No forced mate in {} found", mate).unwrap();
+                }
+            }
+            writeln!(output, "{}", format_best_move(&result)).unwrap();
             output.flush().unwrap();
         }
         Err(e) => panic!("Unexpected error during move search: {}", e),
     }
 }
 
-fn stop<W: Write + Send + 'static>(_state: ArcMutexUCIState, _output: W) {
-    // TODO: Implement early stopping
+fn format_best_move(result: &SearchResult) -> String {
+    let best_move = result.best_move.to_long_algebraic_notation();
+    // The move that we expect the opponent to reply with is pondered on
+    match result.pv.get(1) {
+        Some(ponder_move) => format!(
+            "bestmove {} ponder {}",
+            best_move,
+            ponder_move.to_long_algebraic_notation()
+        ),
+        None => format!("bestmove {}", best_move),
+    }
 }
 
-fn ponderhit<W: Write + Send + 'static>(_state: ArcMutexUCIState, _output: W) {
-    // TODO: Implement early stopping
+fn format_search_report(report: &SearchReport) -> String {
+    match report {
+        SearchReport::Iteration {
+            depth,
+            seldepth,
+            multipv,
+            score,
+            nodes,
+            hashfull,
+            time,
+            pv,
+        } => {
+            let score_string = match score {
+                SearchScore::Centipawns(cp) => format!("cp {}", cp),
+                SearchScore::Mate(moves) => format!("mate {}", moves),
+            };
+            let time_ms = time.as_millis() as u64;
+            // Avoid dividing by zero for very quick iterations
+            let nps = *nodes * 1000 / time_ms.max(1);
+            let pv_string = pv
+                .iter()
+                .map(|m| m.to_long_algebraic_notation())
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!(
+                "info depth {} seldepth {} multipv {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+                depth, seldepth, multipv, score_string, nodes, nps, hashfull, time_ms, pv_string
+            )
+        }
+        SearchReport::CurrentMove {
+            depth,
+            current_move,
+            move_number,
+        } => format!(
+            "info depth {} currmove {} currmovenumber {}",
+            depth,
+            current_move.to_long_algebraic_notation(),
+            move_number
+        ),
+    }
+}
+
+fn stop<W: Write + Send + 'static>(state: ArcMutexUCIState, _output: W) {
+    // The search thread replies with the best move once it notices the signal
+    state
+        .lock()
+        .unwrap()
+        .stop_signal
+        .store(true, Ordering::SeqCst);
+}
+
+fn ponderhit<W: Write + Send + 'static>(state: ArcMutexUCIState, _output: W) {
+    // The opponent played the expected move, so the search that is pondering
+    // continues as a normal search and its time limits start to apply
+    state
+        .lock()
+        .unwrap()
+        .ponder_signal
+        .store(false, Ordering::SeqCst);
+}
+
+fn savehash<W: Write + Send + 'static>(state: ArcMutexUCIState, mut output: W, path: String) {
+    let tt = state.lock().unwrap().tt.clone();
+    match tt.save(Path::new(&path)) {
+        Ok(_) => writeln!(output, "info string Saved hash to {}", path).unwrap(),
+        Err(e) => writeln!(output, "info string Unable to save hash to {}: {}", path, e).unwrap(),
+    }
+    output.flush().unwrap();
+}
+
+fn loadhash<W: Write + Send + 'static>(state: ArcMutexUCIState, mut output: W, path: String) {
+    let tt = state.lock().unwrap().tt.clone();
+    match tt.load(Path::new(&path)) {
+        Ok(_) => writeln!(output, "info string Loaded hash from {}", path).unwrap(),
+        Err(e) => writeln!(
+            output,
+            "info string Unable to load hash from {}: {}",
+            path, e
+        )
+        .unwrap(),
+    }
+    output.flush().unwrap();
 }
 
 fn apply_moves_and_set_state<W: Write + Send + 'static>(
@@ -263,8 +445,18 @@ fn set_option<W: Write + Send + 'static>(
     // Search for the option
     for uci_option in UCI_OPTIONS.iter() {
         if arg_name == uci_option.name {
-            match args_val.parse::<u32>() {
-                Ok(val) => {
+            let parsed_val = match uci_option.option_type {
+                UCIOptionType::Spin => args_val.parse::<u32>().ok(),
+                UCIOptionType::Check => match args_val.as_str() {
+                    "true" => Some(1),
+                    "false" => Some(0),
+                    _ => None,
+                },
+                // Buttons do not have a value
+                UCIOptionType::Button => Some(0),
+            };
+            match parsed_val {
+                Some(val) => {
                     if val > uci_option.max || val < uci_option.min {
                         writeln!(output, "Option not in range for {}", arg_name).unwrap();
                         output.flush().unwrap();
@@ -276,13 +468,41 @@ fn set_option<W: Write + Send + 'static>(
                     // Consider using a HashMap when more options come into play
                     if arg_name == "NumThreads" {
                         state.num_threads = val as usize;
+                    } else if arg_name == "Hash" {
+                        if state.tt.size_mb() != val as usize {
+                            state.tt = build_new_tt(val as usize);
+                        }
+                    } else if arg_name == "Clear Hash" {
+                        state.tt.clear();
+                    } else if arg_name == "MultiPV" {
+                        state.multi_pv = val as usize;
+                    } else if arg_name == "RecaptureExtension" {
+                        state.search_params.recapture_extension = val != 0;
+                    } else if arg_name == "PassedPawnExtension" {
+                        state.search_params.passed_pawn_extension = val != 0;
+                    } else if arg_name == "ReverseFutilityMargin" {
+                        state.search_params.reverse_futility_margin = val as i32;
+                    } else if arg_name == "RazoringMargin" {
+                        state.search_params.razoring_margin = val as i32;
+                    } else if arg_name == "ProbCutMargin" {
+                        state.search_params.probcut_margin = val as i32;
+                    } else if arg_name == "QuiescenceChecks" {
+                        state.search_params.quiescence_checks = val != 0;
+                    } else if arg_name == "Ponder" {
+                        // Nothing to configure, the GUI decides when
+                        // to ponder by sending `go ponder`
                     } else {
                         writeln!(output, "Unexpected option {}", arg_name).unwrap();
                         output.flush().unwrap();
                     }
                 }
-                Err(_) => {
-                    writeln!(output, "Expected an integer option for {}", arg_name).unwrap();
+                None => {
+                    let expected_type = match uci_option.option_type {
+                        UCIOptionType::Spin => "an integer",
+                        UCIOptionType::Check => "a boolean",
+                        UCIOptionType::Button => unreachable!("Buttons do not have a value"),
+                    };
+                    writeln!(output, "Expected {} option for {}", expected_type, arg_name).unwrap();
                     output.flush().unwrap();
                 }
             }
@@ -314,6 +534,16 @@ mod test {
                 "id name Requin v1.3.0\n",
                 "id author James Tan\n",
                 "option name NumThreads type spin default 16 min 1 max 32\n",
+                "option name Hash type spin default 16 min 1 max 65536\n",
+                "option name Clear Hash type button\n",
+                "option name Ponder type check default false\n",
+                "option name MultiPV type spin default 1 min 1 max 256\n",
+                "option name RecaptureExtension type check default true\n",
+                "option name PassedPawnExtension type check default true\n",
+                "option name ReverseFutilityMargin type spin default 150 min 0 max 5000\n",
+                "option name RazoringMargin type spin default 400 min 0 max 5000\n",
+                "option name ProbCutMargin type spin default 200 min 0 max 5000\n",
+                "option name QuiescenceChecks type check default true\n",
                 "uciok\n"
             ]
             .join("")
@@ -415,6 +645,242 @@ mod test {
         assert_eq!(expected_board, board);
     }
 
+    #[test]
+    fn handle_stop_during_go() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+
+        let go_state = Arc::clone(&state);
+        let go_output = output_buffer.clone();
+        let handle = std::thread::spawn(move || {
+            go(
+                go_state,
+                go_output,
+                GoArgs::new_from_args_str(" depth 50".into()).unwrap(),
+            );
+        });
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        stop(Arc::clone(&state), output_buffer.clone());
+        handle.join().unwrap();
+
+        let output = output_buffer.get_inner().lock().unwrap().clone();
+        let output = std::str::from_utf8(&output).unwrap();
+        assert!(output.lines().last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn format_iteration_report() {
+        let report = SearchReport::Iteration {
+            depth: 3,
+            seldepth: 7,
+            multipv: 1,
+            score: SearchScore::Centipawns(-25),
+            nodes: 5000,
+            hashfull: 12,
+            time: std::time::Duration::from_millis(250),
+            pv: vec![Board::new_starting_pos()
+                .build_move_with_src_dest(Coordinate::E2, Coordinate::E4, None)
+                .unwrap()],
+        };
+
+        assert_eq!(
+            format_search_report(&report),
+            "info depth 3 seldepth 7 multipv 1 score cp -25 nodes 5000 nps 20000 hashfull 12 time 250 pv e2e4"
+        );
+    }
+
+    #[test]
+    fn format_iteration_report_with_mate_score() {
+        let report = SearchReport::Iteration {
+            depth: 4,
+            seldepth: 4,
+            multipv: 2,
+            score: SearchScore::Mate(-2),
+            nodes: 10,
+            hashfull: 0,
+            time: std::time::Duration::from_millis(0),
+            pv: vec![],
+        };
+
+        assert!(format_search_report(&report)
+            .contains("multipv 2 score mate -2 nodes 10 nps 10000 hashfull 0 time 0"));
+    }
+
+    #[test]
+    fn format_best_move_with_ponder_move() {
+        let mut board = Board::new_starting_pos();
+        let best_move = board
+            .build_move_with_src_dest(Coordinate::E2, Coordinate::E4, None)
+            .unwrap();
+        board.apply_move(&best_move);
+        let ponder_move = board
+            .build_move_with_src_dest(Coordinate::E7, Coordinate::E5, None)
+            .unwrap();
+        let mut result = SearchResult {
+            best_move,
+            score: SearchScore::Centipawns(30),
+            pv: vec![best_move, ponder_move],
+            lines: vec![],
+        };
+
+        assert_eq!(format_best_move(&result), "bestmove e2e4 ponder e7e5");
+
+        // Without a reply in the PV there is nothing to ponder on
+        result.pv.truncate(1);
+        assert_eq!(format_best_move(&result), "bestmove e2e4");
+    }
+
+    #[test]
+    fn format_current_move_report() {
+        let report = SearchReport::CurrentMove {
+            depth: 2,
+            current_move: Board::new_starting_pos()
+                .build_move_with_src_dest(Coordinate::G1, Coordinate::F3, None)
+                .unwrap(),
+            move_number: 5,
+        };
+
+        assert_eq!(
+            format_search_report(&report),
+            "info depth 2 currmove g1f3 currmovenumber 5"
+        );
+    }
+
+    #[test]
+    fn handle_go_reports_iterations() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        go(
+            state,
+            output_buffer.clone(),
+            GoArgs::new_from_args_str(" depth 2".into()).unwrap(),
+        );
+
+        let output = output_buffer.get_inner().lock().unwrap().clone();
+        let output = std::str::from_utf8(&output).unwrap();
+        assert!(output.contains("info depth 1 seldepth"));
+        assert!(output.contains("info depth 2 seldepth"));
+        assert!(output.lines().last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn handle_go_with_invalid_args() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        mockable::UCIHandler::new().handle_go(
+            state,
+            output_buffer.clone(),
+            " wtime 4294967296".into(),
+        );
+
+        // No search is started for arguments that cannot be parsed
+        let output = output_buffer.get_inner().lock().unwrap().clone();
+        let output = std::str::from_utf8(&output).unwrap();
+        assert_eq!(
+            output,
+            "info string Invalid value in the arguments of the go command.\n"
+        );
+    }
+
+    #[test]
+    fn handle_go_with_node_limit() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        go(
+            state,
+            output_buffer.clone(),
+            GoArgs::new_from_args_str(" nodes 5000".into()).unwrap(),
+        );
+
+        let output = output_buffer.get_inner().lock().unwrap().clone();
+        let output = std::str::from_utf8(&output).unwrap();
+        assert!(output.lines().last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn handle_go_with_searchmoves() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        go(
+            state,
+            output_buffer.clone(),
+            GoArgs::new_from_args_str(" searchmoves h2h3 depth 2".into()).unwrap(),
+        );
+
+        let output = output_buffer.get_inner().lock().unwrap().clone();
+        let output = std::str::from_utf8(&output).unwrap();
+        assert!(output
+            .lines()
+            .filter(|l| l.contains(" pv "))
+            .all(|l| l.contains(" pv h2h3")));
+        assert!(output.lines().last().unwrap().starts_with("bestmove h2h3"));
+    }
+
+    #[test]
+    fn handle_go_with_multi_pv() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        set_option(
+            state.clone(),
+            output_buffer.clone(),
+            "MultiPV".into(),
+            "3".into(),
+        );
+        go(
+            state,
+            output_buffer.clone(),
+            GoArgs::new_from_args_str(" depth 2".into()).unwrap(),
+        );
+
+        let output = output_buffer.get_inner().lock().unwrap().clone();
+        let output = std::str::from_utf8(&output).unwrap();
+        for multipv in 1..=3 {
+            let tag = format!(" multipv {} ", multipv);
+            assert!(output
+                .lines()
+                .any(|l| l.starts_with("info depth 2 ") && l.contains(&tag)));
+        }
+        assert!(!output.contains("multipv 4"));
+    }
+
+    #[test]
+    fn handle_go_with_mate() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        position_with_fen(
+            state.clone(),
+            output_buffer.clone(),
+            "r1bq3r/ppp1nQ2/2kp1N2/2b3n1/4P3/8/P2N1PPP/1RR3K1 w - - 0 1".into(),
+            vec![],
+        );
+        go(
+            state.clone(),
+            output_buffer.clone(),
+            GoArgs::new_from_args_str(" mate 2".into()).unwrap(),
+        );
+
+        let output = output_buffer.get_inner().lock().unwrap().clone();
+        let output = std::str::from_utf8(&output).unwrap();
+        let last_info = output
+            .lines()
+            .filter(|l| l.contains(" pv "))
+            .last()
+            .unwrap();
+        assert!(last_info.contains(" score mate 2 "));
+        assert!(!output.contains("info string"));
+
+        output_buffer.get_inner().lock().unwrap().clear();
+        go(
+            state,
+            output_buffer.clone(),
+            GoArgs::new_from_args_str(" mate 1".into()).unwrap(),
+        );
+
+        let output = output_buffer.get_inner().lock().unwrap().clone();
+        let output = std::str::from_utf8(&output).unwrap();
+        assert!(output.contains("info string No forced mate in 1 found\n"));
+    }
+
     #[test]
     fn handle_set_option_invalid_option_name() {
         let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
@@ -479,4 +945,198 @@ mod test {
             "Expected an integer option for NumThreads\n"
         );
     }
+
+    #[test]
+    fn handle_set_option_ponder_not_boolean() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        set_option(
+            state.clone(),
+            output_buffer.clone(),
+            "Ponder".into(),
+            "1".into(),
+        );
+
+        assert_eq!(
+            std::str::from_utf8(&output_buffer.get_inner().lock().unwrap()).unwrap(),
+            "Expected a boolean option for Ponder\n"
+        );
+    }
+
+    #[test]
+    fn handle_set_option_extensions() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        set_option(
+            state.clone(),
+            output_buffer.clone(),
+            "RecaptureExtension".into(),
+            "false".into(),
+        );
+
+        let search_params = state.lock().unwrap().search_params.clone();
+        assert!(!search_params.recapture_extension);
+        assert!(search_params.passed_pawn_extension);
+        assert!(output_buffer.get_inner().lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn handle_set_option_pruning_margins() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        set_option(
+            state.clone(),
+            output_buffer.clone(),
+            "RazoringMargin".into(),
+            "250".into(),
+        );
+
+        let search_params = state.lock().unwrap().search_params.clone();
+        assert_eq!(search_params.razoring_margin, 250);
+        assert_eq!(
+            search_params.probcut_margin,
+            SearchParams::default().probcut_margin
+        );
+        assert!(output_buffer.get_inner().lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn handle_go_ponder_until_ponderhit() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        state
+            .lock()
+            .unwrap()
+            .ponder_signal
+            .store(true, Ordering::SeqCst);
+
+        let go_state = Arc::clone(&state);
+        let go_output = output_buffer.clone();
+        let handle = std::thread::spawn(move || {
+            go(
+                go_state,
+                go_output,
+                GoArgs::new_from_args_str(" ponder depth 2".into()).unwrap(),
+            );
+        });
+        // The search may not end before the ponderhit even though
+        // its depth limit is reached
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        assert!(!handle.is_finished());
+
+        ponderhit(Arc::clone(&state), output_buffer.clone());
+        handle.join().unwrap();
+
+        let output = output_buffer.get_inner().lock().unwrap().clone();
+        let output = std::str::from_utf8(&output).unwrap();
+        assert!(output.lines().last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn handle_set_option_hash_valid() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        set_option(
+            state.clone(),
+            output_buffer.clone(),
+            "Hash".into(),
+            "64".into(),
+        );
+
+        assert_eq!(state.lock().unwrap().tt.size_mb(), 64);
+        assert!(output_buffer.get_inner().lock().unwrap().is_empty());
+    }
+
+    // Whether the TT of the state has an entry for the current position
+    fn has_tt_entry_for_position(state: &ArcMutexUCIState) -> bool {
+        let state = state.lock().unwrap();
+        let zobrist = state.game.as_ref().unwrap().get_current_zobrist();
+        state.tt.get_entry(zobrist).is_valid(zobrist)
+    }
+
+    #[test]
+    fn handle_set_option_clear_hash() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        go(
+            state.clone(),
+            output_buffer.clone(),
+            GoArgs::new_from_args_str(" depth 2".into()).unwrap(),
+        );
+        assert!(has_tt_entry_for_position(&state));
+
+        output_buffer.get_inner().lock().unwrap().clear();
+        set_option(
+            state.clone(),
+            output_buffer.clone(),
+            "Clear Hash".into(),
+            "".into(),
+        );
+
+        assert!(output_buffer.get_inner().lock().unwrap().is_empty());
+        assert!(!has_tt_entry_for_position(&state));
+    }
+
+    #[test]
+    fn handle_go_keeps_tt_until_ucinewgame() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        go(
+            state.clone(),
+            output_buffer.clone(),
+            GoArgs::new_from_args_str(" depth 2".into()).unwrap(),
+        );
+        position_with_startpos(state.clone(), output_buffer.clone(), vec![]);
+
+        // The results of the earlier search are still available
+        assert!(has_tt_entry_for_position(&state));
+
+        ucinewgame(state.clone(), output_buffer.clone());
+        assert!(!has_tt_entry_for_position(&state));
+    }
+
+    #[test]
+    fn handle_savehash_and_loadhash() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        go(
+            state.clone(),
+            output_buffer.clone(),
+            GoArgs::new_from_args_str(" depth 2".into()).unwrap(),
+        );
+        let path = std::env::temp_dir().join(format!("requin_handler_{}.tt", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+
+        output_buffer.get_inner().lock().unwrap().clear();
+        savehash(state.clone(), output_buffer.clone(), path.clone());
+        ucinewgame(state.clone(), output_buffer.clone());
+        assert!(!has_tt_entry_for_position(&state));
+
+        loadhash(state.clone(), output_buffer.clone(), path.clone());
+        std::fs::remove_file(&path).unwrap();
+        assert!(has_tt_entry_for_position(&state));
+        assert_eq!(
+            std::str::from_utf8(&output_buffer.get_inner().lock().unwrap()).unwrap(),
+            format!(
+                "info string Saved hash to {}\ninfo string Loaded hash from {}\n",
+                path, path
+            )
+        );
+    }
+
+    #[test]
+    fn handle_loadhash_missing_file() {
+        let output_buffer: Output<Vec<u8>> = Output::new(vec![]);
+        let state = new_arc_mutex_uci_state();
+        loadhash(
+            state,
+            output_buffer.clone(),
+            "/nonexistent/requin.tt".into(),
+        );
+
+        assert_eq!(
+            std::str::from_utf8(&output_buffer.get_inner().lock().unwrap()).unwrap(),
+            "info string Unable to load hash from /nonexistent/requin.tt: Unable to open the file.\n"
+        );
+    }
 }
@@ -8,7 +8,9 @@ pub use client::Client;
 use go_args::GoArgs;
 pub use output::Output;
 
+use crate::engine::{build_new_tt, SearchParams, TranspositionTable, DEFAULT_TT_SIZE_MB};
 use crate::game::Game;
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
 pub struct UCIState {
@@ -16,6 +18,15 @@ pub struct UCIState {
     game: Option<Game>,
     go_args: Option<GoArgs>,
     num_threads: usize,
+    // Shared by all searches until the next `ucinewgame`
+    tt: TranspositionTable,
+    // Number of best lines to report during searches
+    multi_pv: usize,
+    search_params: SearchParams,
+    // Used to stop the search that is currently running
+    stop_signal: Arc<AtomicBool>,
+    // Set while the current search is pondering, cleared on ponderhit
+    ponder_signal: Arc<AtomicBool>,
 }
 
 impl UCIState {
@@ -25,6 +36,11 @@ impl UCIState {
             game: None,
             go_args: None,
             num_threads: 16,
+            tt: build_new_tt(DEFAULT_TT_SIZE_MB),
+            multi_pv: 1,
+            search_params: SearchParams::default(),
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            ponder_signal: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -56,9 +72,6 @@ impl UCIOption {
         min: u32,
         max: u32,
     ) -> UCIOption {
-        if option_type != UCIOptionType::Spin {
-            panic!("Unsupported UCIOptionType");
-        }
         UCIOption {
             name,
             option_type,
@@ -73,8 +86,10 @@ impl UCIOption {
 enum UCIOptionType {
     // TODO: Implement the rest when they become necessary
     Spin,
-    // Check,
+    // Options that are either true (1) or false (0)
+    Check,
     // Combo,
-    // Button,
+    // Options without a value that trigger an action
+    Button,
     // String,
 }
@@ -4,6 +4,10 @@ use crate::board::{Color, Coordinate, PieceType};
 
 pub type Key = u64;
 
+// Seed of the random numbers that make up the Zobrist keys, keys (and
+// everything derived from them) change whenever this is changed
+pub const ZOBRIST_SEED: u64 = 42;
+
 // Lookup table for Zobrist hash values
 pub struct ZobristTable {
     pieces: [[[Key; 64]; 6]; 2], // One for each piece type of each color for each square
@@ -33,7 +37,7 @@ impl ZobristTable {
 
 lazy_static! {
     pub static ref ZOBRIST_TABLE: ZobristTable = {
-        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(42);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(ZOBRIST_SEED);
 
         let mut t = ZobristTable {
             pieces: [[[0; 64]; 6]; 2],
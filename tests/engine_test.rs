@@ -1,6 +1,6 @@
 use requin::board::*;
-use requin::engine::Searcher;
-use requin::game::Game;
+use requin::engine::{SearchLimits, SearchScore, Searcher};
+use requin::game::{Game, GameState};
 use requin::parser::parse_fen;
 use requin::r#move::Move;
 
@@ -267,6 +267,63 @@ fn white_mate_in_two() {
     assert_eq!(best_move.unwrap(), expected_move);
 }
 
+#[test]
+fn white_mate_in_two_reports_mating_line() {
+    let board = parse_fen(String::from(
+        "r1bq3r/ppp1nQ2/2kp1N2/2b3n1/4P3/8/P2N1PPP/1RR3K1 w - - 0 1",
+    ))
+    .unwrap();
+    let mut game = Game::new(board);
+    let mut searcher = Searcher::new(game.clone(), 3, 32);
+    let result = searcher.search(&SearchLimits::new_with_depth(3)).unwrap();
+
+    assert_eq!(result.score, SearchScore::Mate(2));
+    assert_eq!(result.pv.len(), 3);
+    for m in &result.pv {
+        game.apply_move(m);
+    }
+    assert_eq!(game.state, GameState::WhiteWon);
+}
+
+#[test]
+fn white_mate_in_two_with_mate_search() {
+    let board = parse_fen(String::from(
+        "r1bq3r/ppp1nQ2/2kp1N2/2b3n1/4P3/8/P2N1PPP/1RR3K1 w - - 0 1",
+    ))
+    .unwrap();
+    let mut game = Game::new(board);
+    let mut searcher = Searcher::new(game.clone(), 3, 32);
+    let limits = SearchLimits {
+        mate: Some(2),
+        ..Default::default()
+    };
+    let result = searcher.search(&limits).unwrap();
+
+    assert_eq!(result.score, SearchScore::Mate(2));
+    assert_eq!(result.pv.len(), 3);
+    for m in &result.pv {
+        game.apply_move(m);
+    }
+    assert_eq!(game.state, GameState::WhiteWon);
+}
+
+#[test]
+fn white_mate_in_two_has_no_mate_in_one() {
+    let board = parse_fen(String::from(
+        "r1bq3r/ppp1nQ2/2kp1N2/2b3n1/4P3/8/P2N1PPP/1RR3K1 w - - 0 1",
+    ))
+    .unwrap();
+    let game = Game::new(board);
+    let mut searcher = Searcher::new(game, 3, 32);
+    let limits = SearchLimits {
+        mate: Some(1),
+        ..Default::default()
+    };
+    let result = searcher.search(&limits).unwrap();
+
+    assert!(!matches!(result.score, SearchScore::Mate(1)));
+}
+
 #[test]
 fn white_mate_in_two_v2() {
     let board = parse_fen(String::from(